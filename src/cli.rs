@@ -0,0 +1,372 @@
+//! Parsing the command line.
+
+use std::{ffi::OsString, path::PathBuf};
+
+pub const USAGE: &str = "\
+Usage: brewcode [OPTIONS] [FILE[:LINE[:COLUMN]]]...
+
+Opens each FILE in a tab, with the cursor on LINE and COLUMN if given.
+A FILE of - reads standard input into an untitled document.
+Without any files the last session in the current directory is restored, unless another
+instance is already running.
+Files are opened in the window of an instance that is already running, if there is one.
+With --render-to only the files and the --config given are read, never the session,
+recovery files or other config.
+
+Options:
+  -r, --readonly            Open the files without allowing edits
+      --syntax <NAME>       Highlight the files as NAME, e.g. Rust, instead of detecting it
+      --config <PATH>       Read the config from PATH instead of the user config file
+      --restore             Restore the last session even when files are given
+  -w, --wait                Return only once the files are closed, for use as $EDITOR
+  -n, --new-window          Open a new window even if an instance is already running
+      --render-to <PATH>    Draw a single frame into a PNG at PATH instead of opening a window
+      --size <WIDTHxHEIGHT> Size of the frame drawn by --render-to, 800x600 by default and
+                            at most 16384x16384
+  -h, --help                Print this help
+  -V, --version             Print the version";
+
+/// The largest width or height `--size` accepts, the largest texture most GPUs can draw into.
+const MAX_SIZE: u32 = 16384;
+
+/// Where a file named on the command line comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Path(PathBuf),
+    Stdin,
+}
+
+/// A file named on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileArg {
+    pub source: Source,
+    /// The line and column, counting from 1, to put the cursor on.
+    pub position: Option<(usize, Option<usize>)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub readonly: bool,
+    pub syntax: Option<String>,
+    pub config: Option<PathBuf>,
+    pub restore: bool,
+    pub wait: bool,
+    pub new_window: bool,
+    pub render_to: Option<PathBuf>,
+    /// The width and height of the frame drawn by `render_to`.
+    pub size: Option<(u32, u32)>,
+}
+
+#[derive(Debug)]
+pub enum Parsed {
+    Run(Args),
+    Help,
+    Version,
+}
+
+/// Parses the arguments after the program name. Errors are messages for the user.
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Parsed, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    let mut only_files = false;
+
+    while let Some(arg) = args.next() {
+        let text = match arg.to_str() {
+            Some(text) if !only_files => text,
+            // Paths needn't be valid UTF-8, but options are.
+            _ => {
+                parsed.files.push(file_arg(arg));
+                continue;
+            }
+        };
+        // Options taking a value accept it either after `=` or as the next argument.
+        let (option, inline_value) = match text.find('=') {
+            Some(index) if text.starts_with("--") => (&text[..index], Some(&text[index + 1..])),
+            _ => (text, None),
+        };
+        let mut value = |name: &str| -> Result<OsString, String> {
+            match inline_value {
+                Some(value) => Ok(OsString::from(value)),
+                None => args.next().ok_or_else(|| format!("{} needs a value", name)),
+            }
+        };
+
+        match option {
+            "-h" | "--help" => {
+                no_value(option, inline_value)?;
+                return Ok(Parsed::Help);
+            }
+            "-V" | "--version" => {
+                no_value(option, inline_value)?;
+                return Ok(Parsed::Version);
+            }
+            "-r" | "--readonly" => {
+                no_value(option, inline_value)?;
+                parsed.readonly = true;
+            }
+            "--restore" => {
+                no_value(option, inline_value)?;
+                parsed.restore = true;
+            }
+            "-w" | "--wait" => {
+                no_value(option, inline_value)?;
+                parsed.wait = true;
+            }
+            "-n" | "--new-window" => {
+                no_value(option, inline_value)?;
+                parsed.new_window = true;
+            }
+            "--syntax" => {
+                let syntax = value(option)?;
+                let syntax = syntax
+                    .into_string()
+                    .map_err(|_| "--syntax needs a syntax name".to_owned())?;
+                parsed.syntax = Some(syntax);
+            }
+            "--config" => parsed.config = Some(PathBuf::from(value(option)?)),
+            "--render-to" => parsed.render_to = Some(PathBuf::from(value(option)?)),
+            "--size" => {
+                let size = value(option)?;
+                let size = size.to_str().and_then(parse_size).ok_or_else(|| {
+                    format!(
+                        "--size needs a width and height in pixels of at most {}, e.g. 800x600",
+                        MAX_SIZE
+                    )
+                })?;
+                parsed.size = Some(size);
+            }
+            "--" => only_files = true,
+            "-" => parsed.files.push(FileArg {
+                source: Source::Stdin,
+                position: None,
+            }),
+            _ if option.starts_with('-') => return Err(format!("unknown option {}", option)),
+            _ => parsed.files.push(file_arg(OsString::from(text))),
+        }
+    }
+
+    if parsed
+        .files
+        .iter()
+        .filter(|file| file.source == Source::Stdin)
+        .count()
+        > 1
+    {
+        return Err("standard input can only be read once".to_owned());
+    }
+    if parsed.restore && parsed.render_to.is_some() {
+        return Err("--restore doesn't apply to --render-to".to_owned());
+    }
+    if parsed.size.is_some() && parsed.render_to.is_none() {
+        return Err("--size only applies to --render-to".to_owned());
+    }
+    Ok(Parsed::Run(parsed))
+}
+
+fn no_value(option: &str, value: Option<&str>) -> Result<(), String> {
+    match value {
+        Some(_) => Err(format!("{} doesn't take a value", option)),
+        None => Ok(()),
+    }
+}
+
+/// Splits a `:line` or `:line:column` suffix off `arg`, unless a file with the whole name exists.
+fn file_arg(arg: OsString) -> FileArg {
+    let path = PathBuf::from(arg);
+    let position = path
+        .to_str()
+        .filter(|_| !path.exists())
+        .and_then(split_position);
+    match position {
+        Some((name, line, col)) => FileArg {
+            source: Source::Path(PathBuf::from(name)),
+            position: Some((line, col)),
+        },
+        None => FileArg {
+            source: Source::Path(path),
+            position: None,
+        },
+    }
+}
+
+/// Splits "name:line:column" or "name:line" into its parts.
+fn split_position(text: &str) -> Option<(&str, usize, Option<usize>)> {
+    let (rest, last) = text.rsplit_once(':')?;
+    let last = parse_number(last)?;
+    if let Some((name, line)) = rest.rsplit_once(':') {
+        if let (false, Some(line)) = (name.is_empty(), parse_number(line)) {
+            return Some((name, line, Some(last)));
+        }
+    }
+    if rest.is_empty() {
+        None
+    } else {
+        Some((rest, last, None))
+    }
+}
+
+/// Parses "WIDTHxHEIGHT", neither of which may be 0.
+fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once('x')?;
+    let dimension = |text| {
+        parse_number(text)
+            .filter(|&n| n > 0 && n <= MAX_SIZE as usize)
+            .map(|n| n as u32)
+    };
+    Some((dimension(width)?, dimension(height)?))
+}
+
+fn parse_number(text: &str) -> Option<usize> {
+    if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::Path, process};
+
+    fn run(args: &[&str]) -> Result<Args, String> {
+        match parse(args.iter().map(OsString::from))? {
+            Parsed::Run(args) => Ok(args),
+            parsed => panic!("expected to run, got {:?}", parsed),
+        }
+    }
+
+    fn path(file: &FileArg) -> &Path {
+        match &file.source {
+            Source::Path(path) => path,
+            Source::Stdin => panic!("expected a path"),
+        }
+    }
+
+    #[test]
+    fn files_with_positions() {
+        let args = run(&["a.rs:12:5", "b.rs:7", "c.rs", "-"]).unwrap();
+        let files = &args.files;
+        assert_eq!(path(&files[0]), Path::new("a.rs"));
+        assert_eq!(files[0].position, Some((12, Some(5))));
+        assert_eq!(path(&files[1]), Path::new("b.rs"));
+        assert_eq!(files[1].position, Some((7, None)));
+        assert_eq!(files[2].position, None);
+        assert_eq!(files[3].source, Source::Stdin);
+    }
+
+    #[test]
+    fn existing_files_keep_their_colons() {
+        let dir = env::temp_dir().join(format!("brewcode-cli-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes:12");
+        fs::write(&file, "").unwrap();
+        let args = run(&[file.to_str().unwrap()]).unwrap();
+        assert_eq!(path(&args.files[0]), file);
+        assert_eq!(args.files[0].position, None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn options() {
+        let args = run(&["-r", "--syntax=Rust", "--config", "my.toml", "-n", "--wait"]).unwrap();
+        assert!(args.readonly && args.new_window && args.wait);
+        assert_eq!(args.syntax.as_deref(), Some("Rust"));
+        assert_eq!(args.config, Some(PathBuf::from("my.toml")));
+
+        let args = run(&["--render-to=out.png", "--size", "640x480"]).unwrap();
+        assert_eq!(args.render_to, Some(PathBuf::from("out.png")));
+        assert_eq!(args.size, Some((640, 480)));
+
+        assert!(matches!(
+            parse(vec![OsString::from("-h")]),
+            Ok(Parsed::Help)
+        ));
+        assert!(matches!(
+            parse(vec![OsString::from("--version")]),
+            Ok(Parsed::Version)
+        ));
+    }
+
+    #[test]
+    fn everything_after_a_double_dash_is_a_file() {
+        let args = run(&["--", "-r", "--size=1x1", "-"]).unwrap();
+        assert!(!args.readonly);
+        let paths: Vec<&Path> = args.files.iter().map(path).collect();
+        assert_eq!(
+            paths,
+            [Path::new("-r"), Path::new("--size=1x1"), Path::new("-")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_need_not_be_utf8() {
+        use std::os::unix::ffi::OsStringExt;
+        let name = OsString::from_vec(b"caf\xe9.txt".to_vec());
+        let args = match parse(vec![name.clone()]).unwrap() {
+            Parsed::Run(args) => args,
+            parsed => panic!("expected to run, got {:?}", parsed),
+        };
+        assert_eq!(path(&args.files[0]), Path::new(&name));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |args: &[&str]| run(args).unwrap_err();
+        assert_eq!(error(&["-", "-"]), "standard input can only be read once");
+        assert_eq!(
+            error(&["--size=800x600"]),
+            "--size only applies to --render-to"
+        );
+        assert_eq!(
+            error(&["--restore", "--render-to", "a.png"]),
+            "--restore doesn't apply to --render-to"
+        );
+        assert_eq!(
+            error(&["--readonly=yes"]),
+            "--readonly doesn't take a value"
+        );
+        assert_eq!(error(&["--syntax"]), "--syntax needs a value");
+        assert_eq!(error(&["--bogus"]), "unknown option --bogus");
+        assert!(error(&["--render-to=a.png", "--size=800"]).starts_with("--size needs"));
+        assert!(error(&["--render-to=a.png", "--size=20000x600"]).starts_with("--size needs"));
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(split_position("a:1"), Some(("a", 1, None)));
+        assert_eq!(split_position("a:1:2"), Some(("a", 1, Some(2))));
+        // Only numbers count, so earlier colons belong to the name.
+        assert_eq!(split_position("a:b:2"), Some(("a:b", 2, None)));
+        assert_eq!(split_position("a::2"), Some(("a:", 2, None)));
+        assert_eq!(split_position(":1:2"), Some((":1", 2, None)));
+        assert_eq!(split_position("a"), None);
+        assert_eq!(split_position("a:"), None);
+        assert_eq!(split_position("a:x"), None);
+        assert_eq!(split_position(":1"), None);
+        assert_eq!(split_position("a:+1"), None);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("800x600"), Some((800, 600)));
+        assert_eq!(parse_size("1x1"), Some((1, 1)));
+        assert_eq!(parse_size("16384x16384"), Some((16384, 16384)));
+        for text in &[
+            "0x600",
+            "800x0",
+            "800x",
+            "x600",
+            "800",
+            "800X600",
+            "800x600x2",
+            "-1x5",
+            "16385x600",
+            "800x16385",
+            "99999999999x1",
+        ] {
+            assert_eq!(parse_size(text), None, "{:?}", text);
+        }
+    }
+}
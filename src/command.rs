@@ -0,0 +1,149 @@
+/// Everything the editor can be told to do from the keyboard. Each command has a stable name
+/// that the keymap and the config refer to it by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    ShowCommands,
+    FindFile,
+    ToggleExplorer,
+    FocusExplorer,
+    NewFile,
+    NewFolder,
+    RenameEntry,
+    DeleteEntry,
+    Quit,
+    Save,
+    SaveAs,
+    Rename,
+    UseLf,
+    UseCrLf,
+    ToggleAutosave,
+    Undo,
+    Redo,
+    SplitHorizontal,
+    SplitVertical,
+    ClosePane,
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    GoToLine,
+    GoToSymbol,
+}
+
+impl Command {
+    pub const ALL: &'static [Command] = &[
+        Command::ShowCommands,
+        Command::FindFile,
+        Command::ToggleExplorer,
+        Command::FocusExplorer,
+        Command::NewFile,
+        Command::NewFolder,
+        Command::RenameEntry,
+        Command::DeleteEntry,
+        Command::Quit,
+        Command::Save,
+        Command::SaveAs,
+        Command::Rename,
+        Command::UseLf,
+        Command::UseCrLf,
+        Command::ToggleAutosave,
+        Command::Undo,
+        Command::Redo,
+        Command::SplitHorizontal,
+        Command::SplitVertical,
+        Command::ClosePane,
+        Command::FocusLeft,
+        Command::FocusRight,
+        Command::FocusUp,
+        Command::FocusDown,
+        Command::CursorLeft,
+        Command::CursorRight,
+        Command::CursorUp,
+        Command::CursorDown,
+        Command::GoToLine,
+        Command::GoToSymbol,
+    ];
+
+    /// The name used in the `[keys]` table of the config, e.g. "file.save".
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::ShowCommands => "palette.commands",
+            Command::FindFile => "palette.files",
+            Command::ToggleExplorer => "explorer.toggle",
+            Command::FocusExplorer => "explorer.focus",
+            Command::NewFile => "explorer.new_file",
+            Command::NewFolder => "explorer.new_folder",
+            Command::RenameEntry => "explorer.rename",
+            Command::DeleteEntry => "explorer.delete",
+            Command::Quit => "app.quit",
+            Command::Save => "file.save",
+            Command::SaveAs => "file.save_as",
+            Command::Rename => "file.rename",
+            Command::UseLf => "file.use_lf",
+            Command::UseCrLf => "file.use_crlf",
+            Command::ToggleAutosave => "file.toggle_autosave",
+            Command::Undo => "edit.undo",
+            Command::Redo => "edit.redo",
+            Command::SplitHorizontal => "pane.split_horizontal",
+            Command::SplitVertical => "pane.split_vertical",
+            Command::ClosePane => "pane.close",
+            Command::FocusLeft => "pane.focus_left",
+            Command::FocusRight => "pane.focus_right",
+            Command::FocusUp => "pane.focus_up",
+            Command::FocusDown => "pane.focus_down",
+            Command::CursorLeft => "cursor.left",
+            Command::CursorRight => "cursor.right",
+            Command::CursorUp => "cursor.up",
+            Command::CursorDown => "cursor.down",
+            Command::GoToLine => "go.line",
+            Command::GoToSymbol => "go.symbol",
+        }
+    }
+
+    /// What the command palette lists the command as.
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::ShowCommands => "Show All Commands",
+            Command::FindFile => "Go to File",
+            Command::ToggleExplorer => "Toggle File Explorer",
+            Command::FocusExplorer => "Focus File Explorer",
+            Command::NewFile => "New File",
+            Command::NewFolder => "New Folder",
+            Command::RenameEntry => "Rename Selected in Explorer",
+            Command::DeleteEntry => "Delete Selected in Explorer",
+            Command::Quit => "Quit",
+            Command::Save => "Save",
+            Command::SaveAs => "Save As",
+            Command::Rename => "Rename File",
+            Command::UseLf => "Use LF Line Endings",
+            Command::UseCrLf => "Use CRLF Line Endings",
+            Command::ToggleAutosave => "Toggle Auto-Save on Focus Loss",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::SplitHorizontal => "Split Pane Side by Side",
+            Command::SplitVertical => "Split Pane Stacked",
+            Command::ClosePane => "Close Pane",
+            Command::FocusLeft => "Focus Pane to the Left",
+            Command::FocusRight => "Focus Pane to the Right",
+            Command::FocusUp => "Focus Pane Above",
+            Command::FocusDown => "Focus Pane Below",
+            Command::CursorLeft => "Move Cursor Left",
+            Command::CursorRight => "Move Cursor Right",
+            Command::CursorUp => "Move Cursor Up",
+            Command::CursorDown => "Move Cursor Down",
+            Command::GoToLine => "Go to Line",
+            Command::GoToSymbol => "Go to Symbol in File",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|command| command.name() == name)
+    }
+}
@@ -0,0 +1,531 @@
+use crate::command::Command;
+use crate::dirs;
+use crate::keymap::{self, Chord};
+use crate::toml;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Name of the config file teams can commit to the root of a project.
+const PROJECT_CONFIG: &str = ".brewcode.toml";
+
+/// How finished frames are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Waits for the display to refresh, never tearing.
+    Vsync,
+    /// Shows frames right away, which may tear.
+    Immediate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colors {
+    pub background: [f32; 4],
+    pub gutter: [f32; 4],
+    pub line_number: [f32; 4],
+    pub active_line_number: [f32; 4],
+    pub active_line: [f32; 4],
+    pub selection: [f32; 4],
+    pub cursor: [f32; 4],
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            background: [0.03, 0.03, 0.03, 1.0],
+            gutter: [0.06, 0.06, 0.06, 1.0],
+            line_number: [0.4, 0.4, 0.4, 1.0],
+            active_line_number: [1.0, 1.0, 1.0, 1.0],
+            active_line: [1.0, 1.0, 1.0, 0.05],
+            selection: [0.0, 0.0, 1.0, 0.1],
+            cursor: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Settings that can be overridden for each language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageSettings {
+    pub tab_size: usize,
+    pub insert_spaces: bool,
+}
+
+impl Default for LanguageSettings {
+    fn default() -> Self {
+        Self {
+            tab_size: 4,
+            insert_spaces: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LanguageOverrides {
+    tab_size: Option<usize>,
+    insert_spaces: Option<bool>,
+}
+
+/// Everything a view needs to know to lay out and draw a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewSettings {
+    pub font_size: f32,
+    pub padding: f32,
+    /// Horizontal advance of a character, assuming a monospace font.
+    pub char_width: f32,
+    pub colors: Colors,
+    pub language: LanguageSettings,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            font_size: config.font_size,
+            padding: config.padding,
+            // Advance of the bundled Ubuntu Mono at the default size.
+            char_width: 19.065777,
+            colors: config.colors,
+            language: config.language,
+        }
+    }
+}
+
+/// Settings read from the config files. Each file only needs to mention what it changes: the
+/// defaults are overridden by the user's config, then the project's `.brewcode.toml`, then the
+/// `[languages.<name>]` tables for the language of each document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Path of a TrueType font to use instead of the bundled one.
+    pub font: Option<PathBuf>,
+    pub font_size: f32,
+    /// One of the themes bundled with syntect.
+    pub theme: String,
+    pub padding: f32,
+    pub present_mode: PresentMode,
+    pub autosave_on_focus_loss: bool,
+    pub colors: Colors,
+    pub language: LanguageSettings,
+    // Keyed by lowercase syntax name, e.g. "rust" or "python".
+    languages: HashMap<String, LanguageOverrides>,
+    /// Changes to the default keymap from the `[keys]` tables, file by file in the order the
+    /// files were read. A file can only mention each chord once, so the order within a file
+    /// doesn't matter. `None` unbinds the chord.
+    pub keys: Vec<(Chord, Option<Command>)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            font: None,
+            font_size: 40.0,
+            theme: "Solarized (dark)".to_owned(),
+            padding: 10.0,
+            present_mode: PresentMode::Vsync,
+            autosave_on_focus_loss: false,
+            colors: Colors::default(),
+            language: LanguageSettings::default(),
+            languages: HashMap::new(),
+            keys: vec![],
+        }
+    }
+}
+
+/// The config files that are read, lowest priority first. They don't need to exist. `user_file`
+/// is read instead of the one in the config directory, e.g. when given with `--config`.
+pub fn sources(user_file: Option<&Path>) -> Vec<PathBuf> {
+    let mut sources = vec![];
+    match user_file {
+        Some(path) => sources.push(path.to_owned()),
+        None => {
+            if let Some(dir) = dirs::config_dir() {
+                sources.push(dir.join("config.toml"));
+            }
+        }
+    }
+    sources.push(PathBuf::from(PROJECT_CONFIG));
+    sources
+}
+
+/// Parses a colour written as "#rrggbb" or "#rrggbbaa".
+fn parse_color(text: &str) -> Option<[f32; 4]> {
+    let hex = text.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut color = [1.0; 4];
+    for (index, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        let byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+        *channel = byte as f32 / 255.0;
+    }
+    Some(color)
+}
+
+// Helpers that check the type of a value, recording an error that names the key if it is wrong.
+
+fn expect_number(key: &str, value: &Value, errors: &mut Vec<String>) -> Option<f32> {
+    match value.as_f64() {
+        Some(number) if number >= 0.0 => Some(number as f32),
+        _ => {
+            errors.push(format!("`{}` must be a number of 0 or more", key));
+            None
+        }
+    }
+}
+
+fn expect_positive_number(key: &str, value: &Value, errors: &mut Vec<String>) -> Option<f32> {
+    match value.as_f64() {
+        Some(number) if number > 0.0 => Some(number as f32),
+        _ => {
+            errors.push(format!("`{}` must be a number greater than 0", key));
+            None
+        }
+    }
+}
+
+fn expect_bool(key: &str, value: &Value, errors: &mut Vec<String>) -> Option<bool> {
+    let boolean = value.as_bool();
+    if boolean.is_none() {
+        errors.push(format!("`{}` must be true or false", key));
+    }
+    boolean
+}
+
+fn expect_string<'v>(key: &str, value: &'v Value, errors: &mut Vec<String>) -> Option<&'v str> {
+    let string = value.as_str();
+    if string.is_none() {
+        errors.push(format!("`{}` must be a string", key));
+    }
+    string
+}
+
+fn expect_table<'v>(
+    key: &str,
+    value: &'v Value,
+    errors: &mut Vec<String>,
+) -> Option<&'v Map<String, Value>> {
+    let table = value.as_object();
+    if table.is_none() {
+        errors.push(format!("`{}` must be a table", key));
+    }
+    table
+}
+
+fn expect_tab_size(key: &str, value: &Value, errors: &mut Vec<String>) -> Option<usize> {
+    match value.as_u64() {
+        Some(size) if (1..=16).contains(&size) => Some(size as usize),
+        _ => {
+            errors.push(format!("`{}` must be a whole number from 1 to 16", key));
+            None
+        }
+    }
+}
+
+impl Config {
+    /// Reads every config file, see [`sources`], on top of the defaults. Problems are returned as messages naming
+    /// the file, and whatever was valid is still applied.
+    pub fn load(user_file: Option<&Path>) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = vec![];
+        for path in sources(user_file) {
+            if let Err(message) = config.load_file(&path) {
+                errors.push(message);
+            }
+        }
+        (config, errors)
+    }
+
+    /// Reads just `file`, if given, on top of the defaults, leaving the user and project config
+    /// out.
+    pub fn load_only(file: Option<&Path>) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let errors = file
+            .and_then(|path| config.load_file(path).err())
+            .into_iter()
+            .collect();
+        (config, errors)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(format!("{}: {}", path.display(), error)),
+        };
+        let table = toml::parse(&text).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        let mut errors = vec![];
+        self.apply(&table, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{}: {}", path.display(), errors.join(", ")))
+        }
+    }
+
+    fn apply(&mut self, table: &Map<String, Value>, errors: &mut Vec<String>) {
+        for (key, value) in table {
+            match key.as_str() {
+                "font" => {
+                    if let Some(font) = expect_string(key, value, errors) {
+                        self.font = Some(PathBuf::from(font));
+                    }
+                }
+                "font_size" => {
+                    if let Some(size) = expect_positive_number(key, value, errors) {
+                        self.font_size = size.max(4.0);
+                    }
+                }
+                "theme" => {
+                    if let Some(theme) = expect_string(key, value, errors) {
+                        self.theme = theme.to_owned();
+                    }
+                }
+                "padding" => {
+                    if let Some(padding) = expect_number(key, value, errors) {
+                        self.padding = padding;
+                    }
+                }
+                "present_mode" => match value.as_str() {
+                    Some("vsync") => self.present_mode = PresentMode::Vsync,
+                    Some("immediate") => self.present_mode = PresentMode::Immediate,
+                    // wgpu can only ask for FIFO or immediate presentation.
+                    Some("mailbox") => errors.push(
+                        "`present_mode` \"mailbox\" isn't supported, use \"vsync\" or \"immediate\""
+                            .to_owned(),
+                    ),
+                    _ => errors.push(
+                        "`present_mode` must be \"vsync\" or \"immediate\"".to_owned(),
+                    ),
+                },
+                "autosave_on_focus_loss" => {
+                    if let Some(autosave) = expect_bool(key, value, errors) {
+                        self.autosave_on_focus_loss = autosave;
+                    }
+                }
+                "tab_size" => {
+                    if let Some(size) = expect_tab_size(key, value, errors) {
+                        self.language.tab_size = size;
+                    }
+                }
+                "insert_spaces" => {
+                    if let Some(insert_spaces) = expect_bool(key, value, errors) {
+                        self.language.insert_spaces = insert_spaces;
+                    }
+                }
+                "colors" => {
+                    if let Some(colors) = expect_table(key, value, errors) {
+                        self.apply_colors(colors, errors);
+                    }
+                }
+                "languages" => {
+                    if let Some(languages) = expect_table(key, value, errors) {
+                        self.apply_languages(languages, errors);
+                    }
+                }
+                "keys" => {
+                    if let Some(keys) = expect_table(key, value, errors) {
+                        self.apply_keys(keys, errors);
+                    }
+                }
+                _ => errors.push(format!("unknown key `{}`", key)),
+            }
+        }
+    }
+
+    fn apply_colors(&mut self, table: &Map<String, Value>, errors: &mut Vec<String>) {
+        for (key, value) in table {
+            let color = match key.as_str() {
+                "background" => &mut self.colors.background,
+                "gutter" => &mut self.colors.gutter,
+                "line_number" => &mut self.colors.line_number,
+                "active_line_number" => &mut self.colors.active_line_number,
+                "active_line" => &mut self.colors.active_line,
+                "selection" => &mut self.colors.selection,
+                "cursor" => &mut self.colors.cursor,
+                _ => {
+                    errors.push(format!("unknown key `colors.{}`", key));
+                    continue;
+                }
+            };
+            match value.as_str().and_then(parse_color) {
+                Some(parsed) => *color = parsed,
+                None => errors.push(format!(
+                    "`colors.{}` must be a colour like \"#rrggbb\" or \"#rrggbbaa\"",
+                    key
+                )),
+            }
+        }
+    }
+
+    fn apply_languages(&mut self, table: &Map<String, Value>, errors: &mut Vec<String>) {
+        for (language, value) in table {
+            let prefix = format!("languages.{}", language);
+            let settings = match expect_table(&prefix, value, errors) {
+                Some(settings) => settings,
+                None => continue,
+            };
+            let overrides = self.languages.entry(language.to_lowercase()).or_default();
+            for (key, value) in settings {
+                let full_key = format!("{}.{}", prefix, key);
+                match key.as_str() {
+                    "tab_size" => {
+                        if let Some(size) = expect_tab_size(&full_key, value, errors) {
+                            overrides.tab_size = Some(size);
+                        }
+                    }
+                    "insert_spaces" => {
+                        if let Some(insert_spaces) = expect_bool(&full_key, value, errors) {
+                            overrides.insert_spaces = Some(insert_spaces);
+                        }
+                    }
+                    _ => errors.push(format!("unknown key `{}`", full_key)),
+                }
+            }
+        }
+    }
+
+    /// Reads bindings like `"ctrl+k ctrl+c" = "edit.undo"`, or `"ctrl+w" = "none"` to unbind.
+    fn apply_keys(&mut self, table: &Map<String, Value>, errors: &mut Vec<String>) {
+        // The table doesn't keep the order it was written in, so two spellings of the same chord,
+        // like "ctrl+k" and "Ctrl+K", would otherwise override each other in no particular order.
+        let mut seen: Vec<(Chord, &str)> = vec![];
+        for (chord, value) in table {
+            let parsed = match keymap::parse_chord(chord) {
+                Some(parsed) => parsed,
+                None => {
+                    errors.push(format!("`keys.\"{}\"` is not a valid key chord", chord));
+                    continue;
+                }
+            };
+            if let Some((_, first)) = seen.iter().find(|(seen, _)| *seen == parsed) {
+                errors.push(format!(
+                    "`keys.\"{}\"` and `keys.\"{}\"` are the same chord",
+                    first, chord
+                ));
+                continue;
+            }
+            seen.push((parsed.clone(), chord));
+            let command = match value.as_str() {
+                Some("none") => None,
+                Some(name) => match Command::from_name(name) {
+                    Some(command) => Some(command),
+                    None => {
+                        errors.push(format!("unknown command `{}`", name));
+                        continue;
+                    }
+                },
+                None => {
+                    errors.push(format!(
+                        "`keys.\"{}\"` must be a command name or \"none\"",
+                        chord
+                    ));
+                    continue;
+                }
+            };
+            self.keys.push((parsed, command));
+        }
+    }
+
+    /// The settings for documents using the syntax called `syntax`, e.g. "Rust".
+    pub fn language(&self, syntax: &str) -> LanguageSettings {
+        let mut settings = self.language;
+        if let Some(overrides) = self.languages.get(&syntax.to_lowercase()) {
+            settings.tab_size = overrides.tab_size.unwrap_or(settings.tab_size);
+            settings.insert_spaces = overrides.insert_spaces.unwrap_or(settings.insert_spaces);
+        }
+        settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{KeyPress, KeyResult, Keymap};
+
+    fn config(text: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut errors = vec![];
+        config.apply(&toml::parse(text).unwrap(), &mut errors);
+        (config, errors)
+    }
+
+    fn press(text: &str) -> KeyPress {
+        KeyPress::parse(text).unwrap()
+    }
+
+    #[test]
+    fn key_overrides() {
+        let (config, errors) = config(
+            r#"
+[keys]
+"ctrl+s" = "none"
+"ctrl+k ctrl+u" = "edit.undo"
+"#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        let mut keymap = Keymap::new(&config.keys);
+        assert_eq!(keymap.press(press("ctrl+s")), KeyResult::Unbound);
+        assert_eq!(keymap.press(press("ctrl+k")), KeyResult::Pending);
+        assert_eq!(
+            keymap.press(press("ctrl+u")),
+            KeyResult::Command(Command::Undo)
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        let (sizes, errors) = config("font_size = 12.5\npadding = 0");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!((sizes.font_size, sizes.padding), (12.5, 0.0));
+
+        let (_, errors) = config("font_size = 0");
+        assert_eq!(errors, ["`font_size` must be a number greater than 0"]);
+        let (_, errors) = config("padding = -1");
+        assert_eq!(errors, ["`padding` must be a number of 0 or more"]);
+    }
+
+    #[test]
+    fn present_modes() {
+        let (immediate, errors) = config("present_mode = \"immediate\"");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(immediate.present_mode, PresentMode::Immediate);
+        for unsupported in &["mailbox", "no-vsync"] {
+            let (fallback, errors) = config(&format!("present_mode = \"{}\"", unsupported));
+            assert_eq!(errors.len(), 1);
+            assert_eq!(fallback.present_mode, PresentMode::Vsync);
+        }
+    }
+
+    #[test]
+    fn bad_key_bindings() {
+        let (config, errors) = config(
+            r#"
+[keys]
+"ctrl+s" = "file.sav"
+"hyper+s" = "file.save"
+"ctrl+q" = 1
+"#,
+        );
+        assert!(config.keys.is_empty());
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn same_chord_twice_in_one_file() {
+        let (config, errors) = config(
+            r#"
+[keys]
+"ctrl+k" = "file.save"
+"Ctrl+K" = "app.quit"
+"#,
+        );
+        assert_eq!(config.keys.len(), 1);
+        assert_eq!(
+            errors,
+            vec!["`keys.\"Ctrl+K\"` and `keys.\"ctrl+k\"` are the same chord".to_owned()]
+        );
+    }
+}
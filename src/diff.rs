@@ -0,0 +1,138 @@
+/// Lines of unchanged text shown around each change.
+const CONTEXT: usize = 3;
+// Past this many line pairs the longest common subsequence table gets too big, so the whole
+// file is shown as replaced instead.
+const MAX_TABLE_SIZE: usize = 16_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Compares `old` and `new` line by line.
+fn diff_lines<'a>(old: &'a [String], new: &'a [String]) -> Vec<Line<'a>> {
+    // Lines that match at the start and end don't need to go through the table.
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut lines: Vec<Line> = old[..prefix].iter().map(|line| Line::Same(line)).collect();
+
+    if old_middle.len() * new_middle.len() > MAX_TABLE_SIZE {
+        lines.extend(old_middle.iter().map(|line| Line::Removed(line)));
+        lines.extend(new_middle.iter().map(|line| Line::Added(line)));
+    } else {
+        // lengths[i][j] is the length of the longest common subsequence of old_middle[i..] and
+        // new_middle[j..].
+        let width = new_middle.len() + 1;
+        let mut lengths = vec![0usize; (old_middle.len() + 1) * width];
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_middle.len() && j < new_middle.len() {
+            if old_middle[i] == new_middle[j] {
+                lines.push(Line::Same(&old_middle[i]));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                lines.push(Line::Removed(&old_middle[i]));
+                i += 1;
+            } else {
+                lines.push(Line::Added(&new_middle[j]));
+                j += 1;
+            }
+        }
+        lines.extend(old_middle[i..].iter().map(|line| Line::Removed(line)));
+        lines.extend(new_middle[j..].iter().map(|line| Line::Added(line)));
+    }
+
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    lines
+}
+
+/// A unified diff turning `old` into `new`, with `old_name` and `new_name` in the header.
+pub fn unified(old_name: &str, old: &[String], new_name: &str, new: &[String]) -> String {
+    let lines = diff_lines(old, new);
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    // Only changes and the context around them are shown, grouped into hunks.
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut index = 0;
+    while index < changed.len() {
+        let start = changed[index].saturating_sub(CONTEXT);
+        let mut end = changed[index] + 1;
+        // Merge changes whose context would touch.
+        while index + 1 < changed.len() && changed[index + 1] <= end + CONTEXT * 2 {
+            index += 1;
+            end = changed[index] + 1;
+        }
+        let end = (end + CONTEXT).min(lines.len());
+        index += 1;
+
+        // Line numbers of the hunk start in the old and new text, counting from 1.
+        let old_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_count, new_start, new_count
+        ));
+        for line in hunk {
+            let (prefix, text) = match line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            output.push(prefix);
+            output.push_str(text);
+            output.push('\n');
+        }
+    }
+
+    output
+}
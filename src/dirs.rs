@@ -0,0 +1,61 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Where brewcode keeps its own files, such as recovery journals.
+/// Follows `XDG_DATA_HOME`, falling back to `~/.local/share` or `%APPDATA%` on Windows.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("brewcode"))
+}
+
+/// A file name standing for `path`: its absolute form with separators replaced by '%', the way
+/// vim names its swap files.
+pub fn escaped_file_name(path: &Path) -> Option<String> {
+    let absolute = fs::canonicalize(path)
+        .or_else(|_| env::current_dir().map(|dir| dir.join(path)))
+        .ok()?;
+    Some(
+        absolute
+            .to_string_lossy()
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' => '%',
+                c => c,
+            })
+            .collect(),
+    )
+}
+
+/// Points the data directory at one of the tests' own, so they don't touch the user's files.
+#[cfg(test)]
+pub fn use_test_data_dir() {
+    static SET: std::sync::Once = std::sync::Once::new();
+    SET.call_once(|| {
+        let dir = env::temp_dir().join(format!("brewcode-test-data-{}", std::process::id()));
+        env::set_var("XDG_DATA_HOME", dir);
+    });
+}
+
+/// Where brewcode keeps files that only matter while it runs, such as its socket. Follows
+/// `XDG_RUNTIME_DIR`, falling back to the data directory.
+pub fn runtime_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => Some(PathBuf::from(dir).join("brewcode")),
+        None => data_dir(),
+    }
+}
+
+/// Where the user's settings live. Follows `XDG_CONFIG_HOME`, falling back to `~/.config` or
+/// `%APPDATA%` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("brewcode"))
+}
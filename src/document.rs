@@ -0,0 +1,904 @@
+use crate::encoding::{self, FileFormat, LineEnding};
+use crate::recovery::Journal;
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use syntect::{
+    highlighting::{HighlightState, Highlighter, RangedHighlightIterator, Style, Theme, ThemeSet},
+    parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    // Row must be before col so that ordering is done properly!
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Location {
+    pub fn new() -> Self {
+        Self { row: 0, col: 0 }
+    }
+
+    /// Moves this location so it points at the same text after `change` was applied.
+    pub fn adjust(&mut self, change: &Change) {
+        if *self < change.start {
+            return;
+        }
+
+        if *self < change.old_end {
+            // The text we pointed at was removed.
+            *self = change.start;
+        } else if self.row == change.old_end.row {
+            self.col = change.new_end.col + (self.col - change.old_end.col);
+            self.row = change.new_end.row;
+        } else {
+            self.row = self.row + change.new_end.row - change.old_end.row;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    /// Creates a new span and ensures that start <= end.
+    pub fn new(start: Location, end: Location) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end),
+        }
+    }
+
+    fn contains_line(&self, line: usize) -> bool {
+        self.start.row <= line && self.end.row >= line
+    }
+
+    pub fn get_char_indices_for_line(
+        &self,
+        line: usize,
+        line_length: usize,
+    ) -> Option<(usize, usize)> {
+        if !self.contains_line(line) {
+            return None;
+        }
+
+        // 4 Cases:
+        // Start/End line
+        // Start line
+        // Entire line
+        // End line
+
+        if self.start.row == self.end.row {
+            Some((self.start.col, self.end.col))
+        } else if self.start.row == line {
+            Some((self.start.col, line_length))
+        } else if self.end.row == line {
+            Some((0, self.end.col))
+        } else {
+            Some((0, line_length))
+        }
+    }
+}
+
+/// Describes how an edit moved text around: everything between `start` and `old_end` was
+/// replaced by text that now ends at `new_end`. Views use this to keep their cursors in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub start: Location,
+    pub old_end: Location,
+    pub new_end: Location,
+}
+
+/// A single step in the undo history.
+#[derive(Debug, Clone)]
+enum Edit {
+    Insert { at: Location, text: String },
+    Delete { at: Location, text: String },
+}
+
+impl Edit {
+    fn inverse(self) -> Self {
+        match self {
+            Edit::Insert { at, text } => Edit::Delete { at, text },
+            Edit::Delete { at, text } => Edit::Insert { at, text },
+        }
+    }
+}
+
+/// Where `text` ends if it is inserted at `at`.
+fn end_of_insert(at: Location, text: &str) -> Location {
+    let newlines = text.matches('\n').count();
+    if newlines == 0 {
+        Location {
+            row: at.row,
+            col: at.col + text.len(),
+        }
+    } else {
+        Location {
+            row: at.row + newlines,
+            col: text.len() - text.rfind('\n').unwrap() - 1,
+        }
+    }
+}
+
+/// The contents of a file: its text, path, highlighting and undo history.
+/// Cursors and scrolling belong to the views showing it.
+pub struct Document {
+    // TODO: Chunk this at maybe a few thousand lines per chunk?
+    lines: Vec<String>,
+    // Ughh, we have to keep this in sync with the lines vec.
+    // I don't really want to create a monolith line struct which contains all this info though.
+    // We will see how much of a pain it is to maintain this here, if its too difficult maybe we will combine?
+    highlight_info: Vec<Vec<(Range<usize>, [f32; 4])>>,
+    // `None` for untitled documents that have never been saved.
+    path: Option<PathBuf>,
+    // Title for documents without a path, such as a diff. Defaults to "untitled".
+    name: Option<String>,
+    // Names the recovery journal of a document without a path, once it has one.
+    untitled_journal: Option<String>,
+    // Modification time of the file when we last read or wrote it, to notice other programs
+    // changing it. `seen_modified` is the newest on-disk change the user has been told about.
+    disk_modified: Option<SystemTime>,
+    seen_modified: Option<SystemTime>,
+    // Encoding and line endings to write the file with, and what they were on disk.
+    format: FileFormat,
+    saved_format: FileFormat,
+    // Set for files opened with `--readonly`.
+    readonly: bool,
+    // Every edit is tagged with the revision it produced. The document is modified whenever
+    // the current revision differs from the one that was last written to disk.
+    undo_stack: Vec<(Edit, usize)>,
+    redo_stack: Vec<(Edit, usize)>,
+    next_revision: usize,
+    saved_revision: usize,
+    // Bumped every time `highlight_info` is recomputed.
+    highlight_generation: usize,
+    // Name of the syntax used for highlighting, picked from the path and first line.
+    syntax: String,
+    // Name of the theme in `theme_set` used for highlighting.
+    theme: String,
+    // TODO: Move those to editor?
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, so a failed
+/// save never leaves a truncated file behind. The original file's permissions are kept.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // Write through symlinks rather than replacing them.
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let permissions = match fs::metadata(&path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(error),
+    };
+    if permissions
+        .as_ref()
+        .is_some_and(|permissions| permissions.readonly())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the file is read-only",
+        ));
+    }
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let temp_path = path.with_file_name(format!(".{}.brewcode-save", file_name.to_string_lossy()));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&temp_path, permissions)?;
+        }
+        fs::rename(&temp_path, &path)
+    })();
+
+    if result.is_err() {
+        // Best effort, the original error is the one worth reporting.
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Splits decoded file contents into lines.
+fn split_lines(file: &str, format: FileFormat) -> Vec<String> {
+    // Splitting on '\n' rather than using `lines` keeps the trailing empty line, so a final
+    // newline survives a round-trip.
+    let mut lines: Vec<String> = file.split('\n').map(|line| line.to_owned()).collect();
+    // In a CRLF file the '\r' belongs to the line break. Any other '\r' is kept as text.
+    // A CRLF file with a few bare LF lines is written back with CRLF everywhere, see
+    // `FileFormat::mixed_line_endings`.
+    if format.line_ending == LineEnding::CrLf {
+        for line in lines.iter_mut() {
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+    }
+    lines
+}
+
+/// Reads and decodes the file at `path`.
+fn read_file(path: &Path) -> io::Result<(String, FileFormat)> {
+    let bytes = fs::read(path)?;
+    Ok(encoding::decode(&bytes))
+}
+
+/// Something defined in a document, e.g. a function, found by the `entity.name` scope the
+/// syntax gives its name.
+pub struct Symbol {
+    pub name: String,
+    /// What the scope says it is, e.g. "function" or "struct".
+    pub kind: String,
+    pub location: Location,
+}
+
+const DEFAULT_THEME: &str = "Solarized (dark)";
+
+/// Picks the syntax for a file from its extension, falling back to its first line (e.g. a
+/// shebang) and then to plain text.
+fn detect_syntax<'a>(
+    syntax_set: &'a SyntaxSet,
+    path: Option<&Path>,
+    first_line: &str,
+) -> &'a SyntaxReference {
+    path.and_then(|path| path.extension())
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| syntax_set.find_syntax_by_extension(extension))
+        .or_else(|| syntax_set.find_syntax_by_first_line(first_line))
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text())
+}
+
+/// The syntax called `name`, ignoring case, or used for files with the extension `name`.
+fn find_syntax<'a>(syntax_set: &'a SyntaxSet, name: &str) -> Option<&'a SyntaxReference> {
+    syntax_set
+        .syntaxes()
+        .iter()
+        .find(|syntax| syntax.name.eq_ignore_ascii_case(name))
+        .or_else(|| syntax_set.find_syntax_by_extension(name))
+}
+
+/// Whether there is a bundled syntax called `name`, see [`Document::set_syntax`].
+pub fn syntax_exists(name: &str) -> bool {
+    find_syntax(&SyntaxSet::load_defaults_newlines(), name).is_some()
+}
+
+fn generate_highlight_info(
+    lines: &[String],
+    info: &mut Vec<Vec<(Range<usize>, [f32; 4])>>,
+    syntax: &SyntaxReference,
+    syntax_set: &SyntaxSet,
+    theme: &Theme,
+) {
+    info.clear();
+    let highlighter = Highlighter::new(theme);
+    let mut highlight_state = HighlightState::new(&highlighter, Default::default());
+    let mut parse_state = ParseState::new(syntax);
+
+    for line in lines {
+        let ops = parse_state.parse_line(line, syntax_set);
+        let iter = RangedHighlightIterator::new(&mut highlight_state, &ops[..], line, &highlighter);
+        info.push(
+            iter.map(|(Style { foreground, .. }, _, range)| {
+                (
+                    range,
+                    [
+                        foreground.r as f32 / 255.0,
+                        foreground.g as f32 / 255.0,
+                        foreground.b as f32 / 255.0,
+                        foreground.a as f32 / 255.0,
+                    ],
+                )
+            })
+            .collect(),
+        );
+    }
+}
+
+impl Document {
+    /// Opens the file at `path`. A file that doesn't exist yet opens as an empty document that
+    /// is created on the first save.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let (file, format) = match read_file(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                (String::new(), FileFormat::default())
+            }
+            Err(error) => return Err(error),
+        };
+        let mut document = Self::with_text(&file, format, Some(path.into()), None);
+        document.disk_modified = modified_time(path);
+        document.seen_modified = document.disk_modified;
+        Ok(document)
+    }
+
+    /// An empty scratch document without a path.
+    pub fn untitled() -> Self {
+        Self::with_text("", FileFormat::default(), None, None)
+    }
+
+    /// A document without a path holding the contents of a file that didn't come from disk, e.g.
+    /// standard input. `name` is used as its title.
+    pub fn from_bytes(name: String, bytes: &[u8]) -> Self {
+        let (file, format) = encoding::decode(bytes);
+        Self::with_text(&file, format, None, Some(name))
+    }
+
+    /// A document without a path holding `text`, e.g. a generated diff. `name` is used as its
+    /// title and to pick the syntax.
+    pub fn scratch(name: String, text: &str) -> Self {
+        Self::with_text(text, FileFormat::default(), None, Some(name))
+    }
+
+    fn with_text(
+        file: &str,
+        format: FileFormat,
+        path: Option<PathBuf>,
+        name: Option<String>,
+    ) -> Self {
+        let mut lines = split_lines(file, format);
+        // Make sure we have at least one line
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let theme_set = ThemeSet::load_defaults();
+        let syntax_path = path.clone().or_else(|| name.as_ref().map(PathBuf::from));
+        let syntax = detect_syntax(&syntax_set, syntax_path.as_deref(), &lines[0])
+            .name
+            .clone();
+        let mut document = Self {
+            highlight_info: vec![],
+            lines,
+            path,
+            name,
+            untitled_journal: None,
+            disk_modified: None,
+            seen_modified: None,
+            // Line breaks that saving would change make the document modified from the start.
+            format: FileFormat {
+                mixed_line_endings: false,
+                ..format
+            },
+            saved_format: format,
+            readonly: false,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            next_revision: 1,
+            highlight_generation: 0,
+            saved_revision: 0,
+            syntax,
+            theme: DEFAULT_THEME.to_owned(),
+            syntax_set,
+            theme_set,
+        };
+        document.highlight();
+        document
+    }
+
+    /// Points the document at a new path, picking the syntax again in case the extension changed.
+    fn set_path(&mut self, path: PathBuf) {
+        let syntax = detect_syntax(&self.syntax_set, Some(&path), &self.lines[0]);
+        if syntax.name != self.syntax {
+            self.syntax = syntax.name.clone();
+            self.highlight();
+        }
+        self.path = Some(path);
+    }
+
+    /// Writes the document to its path. On failure the file on disk is left untouched and the
+    /// document stays modified.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the document has no path")
+        })?;
+        write_atomically(path, &self.encoded())?;
+        self.disk_modified = modified_time(path);
+        self.seen_modified = self.disk_modified;
+        self.saved_revision = self.revision();
+        self.saved_format = self.format;
+        Ok(())
+    }
+
+    /// Writes the document to `path` and makes that its path from now on.
+    /// On failure the document keeps its old path.
+    pub fn save_as(&mut self, path: PathBuf) -> io::Result<()> {
+        write_atomically(&path, &self.encoded())?;
+        self.disk_modified = modified_time(&path);
+        self.seen_modified = self.disk_modified;
+        self.set_path(path);
+        self.saved_revision = self.revision();
+        self.saved_format = self.format;
+        Ok(())
+    }
+
+    /// The file contents in the document's encoding and line endings.
+    fn encoded(&self) -> Vec<u8> {
+        let text = self.lines.join(self.format.line_ending.as_str());
+        encoding::encode(&text, self.format)
+    }
+
+    /// Changes the line endings used when saving. Counts as a modification until saved.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.format.line_ending = line_ending;
+    }
+
+    /// Moves the file on disk to `path`. Unsaved changes stay unsaved.
+    pub fn rename(&mut self, path: PathBuf) -> io::Result<()> {
+        let old_path = self.path.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the document has no path")
+        })?;
+        match fs::rename(old_path, &path) {
+            Ok(()) => {}
+            // Nothing was written yet, so there is nothing to move.
+            Err(error) if error.kind() == io::ErrorKind::NotFound && !old_path.exists() => {}
+            Err(error) => return Err(error),
+        }
+        self.set_path(path);
+        Ok(())
+    }
+
+    /// Points the document at the path its file was moved to by someone else.
+    pub fn follow_move(&mut self, path: PathBuf) {
+        self.set_path(path);
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Whether another program wrote the file since we last read or saved it.
+    pub fn is_newer_on_disk(&self) -> bool {
+        match &self.path {
+            Some(path) => {
+                let modified = modified_time(path);
+                modified.is_some() && modified != self.disk_modified
+            }
+            None => false,
+        }
+    }
+
+    /// Like `is_newer_on_disk`, but ignores changes already passed on to the user with
+    /// `mark_disk_change_seen`.
+    pub fn has_unseen_disk_change(&self) -> bool {
+        self.is_newer_on_disk()
+            && self.path.as_deref().and_then(modified_time) != self.seen_modified
+    }
+
+    pub fn mark_disk_change_seen(&mut self) {
+        self.seen_modified = self.path.as_deref().and_then(modified_time);
+    }
+
+    /// The text currently on disk, e.g. to compare against.
+    pub fn read_disk_lines(&self) -> io::Result<Vec<String>> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the document has no path")
+        })?;
+        let (file, format) = read_file(path)?;
+        Ok(split_lines(&file, format))
+    }
+
+    /// Replaces the text with what is on disk. Unsaved changes and the undo history are dropped.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the document has no path")
+        })?;
+        let modified = modified_time(path);
+        let (file, format) = read_file(path)?;
+        self.lines = split_lines(&file, format);
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.format = FileFormat {
+            mixed_line_endings: false,
+            ..format
+        };
+        self.saved_format = format;
+        self.disk_modified = modified;
+        self.seen_modified = modified;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.saved_revision = self.revision();
+        self.highlight();
+        Ok(())
+    }
+
+    /// The journal unsaved changes to this document are recovered from: its file, or for an
+    /// untitled document the id it was given with `set_untitled_journal`.
+    pub fn journal(&self) -> Option<Journal> {
+        match (&self.path, &self.untitled_journal) {
+            (Some(path), _) => Some(Journal::File(path.clone())),
+            (None, Some(id)) => Some(Journal::Untitled(id.clone())),
+            (None, None) => None,
+        }
+    }
+
+    pub fn set_untitled_journal(&mut self, id: String) {
+        self.untitled_journal = Some(id);
+    }
+
+    pub fn is_untitled(&self) -> bool {
+        self.path.is_none()
+    }
+
+    /// The name shown for this document, e.g. in its tab.
+    pub fn title(&self) -> &str {
+        self.path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str())
+            .or(self.name.as_deref())
+            .unwrap_or("untitled")
+    }
+
+    /// How the document is stored on disk.
+    pub fn format(&self) -> FileFormat {
+        self.format
+    }
+
+    /// Whether the text or its line endings differ from what was last loaded or saved. Undoing
+    /// back to the saved text makes the document unmodified again.
+    pub fn is_modified(&self) -> bool {
+        self.revision() != self.saved_revision || self.format != self.saved_format
+    }
+
+    /// Whether the file has line endings that saving will make all the same.
+    pub fn normalizes_line_endings(&self) -> bool {
+        self.saved_format.mixed_line_endings
+    }
+
+    /// Identifies the current text. Every edit, undo and redo moves to a different revision.
+    pub fn revision(&self) -> usize {
+        self.undo_stack
+            .last()
+            .map(|&(_, revision)| revision)
+            .unwrap_or(0)
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn line(&self, row: usize) -> &str {
+        &self.lines[row]
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Changes whenever the text or its highlighting does, so drawn lines can be cached until
+    /// then.
+    pub fn highlight_generation(&self) -> usize {
+        self.highlight_generation
+    }
+
+    pub fn highlight_info(&self) -> &[Vec<(Range<usize>, [f32; 4])>] {
+        &self.highlight_info
+    }
+
+    /// The location of the character at `col`, counting in characters, on `row`, clamped to
+    /// the document.
+    pub fn location_at(&self, row: usize, col: usize) -> Location {
+        let row = row.min(self.lines.len() - 1);
+        let line = &self.lines[row];
+        let col = line
+            .char_indices()
+            .nth(col)
+            .map_or(line.len(), |(index, _)| index);
+        Location { row, col }
+    }
+
+    /// Clamps `location` to the nearest position that exists in the document, never inside a
+    /// character.
+    pub fn clamp(&self, location: Location) -> Location {
+        let row = location.row.min(self.lines.len() - 1);
+        let col = self.lines[row].floor_char_boundary(location.col);
+        Location { row, col }
+    }
+
+    /// Inserts `text` at `at`, recording it in the undo history.
+    pub fn insert(&mut self, at: Location, text: &str) -> Change {
+        let change = self.apply(Edit::Insert {
+            at,
+            text: text.to_owned(),
+        });
+        self.record(Edit::Insert {
+            at,
+            text: text.to_owned(),
+        });
+        change
+    }
+
+    /// Deletes the text covered by `span`, recording it in the undo history.
+    pub fn delete(&mut self, span: Span) -> Change {
+        let text = self.text_in(span);
+        let change = self.apply(Edit::Delete {
+            at: span.start,
+            text: text.clone(),
+        });
+        self.record(Edit::Delete {
+            at: span.start,
+            text,
+        });
+        change
+    }
+
+    /// Replaces all of the text with `text`, recording it in the undo history.
+    pub fn replace_all(&mut self, text: &str) {
+        let last_row = self.lines.len() - 1;
+        let end = Location {
+            row: last_row,
+            col: self.lines[last_row].len(),
+        };
+        self.delete(Span::new(Location::new(), end));
+        self.insert(Location::new(), text);
+    }
+
+    /// Reverts the last edit. Returns the change made and where a cursor should end up.
+    pub fn undo(&mut self) -> Option<(Change, Location)> {
+        let (edit, revision) = self.undo_stack.pop()?;
+        let change = self.apply(edit.clone().inverse());
+        self.redo_stack.push((edit, revision));
+        Some((change, change.new_end))
+    }
+
+    /// Reapplies the last undone edit. Returns the change made and where a cursor should end up.
+    pub fn redo(&mut self) -> Option<(Change, Location)> {
+        let (edit, revision) = self.redo_stack.pop()?;
+        let change = self.apply(edit.clone());
+        self.undo_stack.push((edit, revision));
+        Some((change, change.new_end))
+    }
+
+    fn record(&mut self, edit: Edit) {
+        self.redo_stack.clear();
+        let revision = self.next_revision;
+        self.next_revision += 1;
+
+        // Merge runs of typing on a single line so undo doesn't go one character at a time.
+        // Never merge into the saved revision though, or we could never get back to it.
+        let saved_revision = self.saved_revision;
+        if let (
+            Some((Edit::Insert { at, text }, last_revision)),
+            Edit::Insert {
+                at: new_at,
+                text: new_text,
+            },
+        ) = (self.undo_stack.last_mut(), &edit)
+        {
+            if *last_revision != saved_revision
+                && !text.contains('\n')
+                && !new_text.contains('\n')
+                && *new_at == end_of_insert(*at, text)
+            {
+                text.push_str(new_text);
+                *last_revision = revision;
+                return;
+            }
+        }
+
+        self.undo_stack.push((edit, revision));
+    }
+
+    fn text_in(&self, span: Span) -> String {
+        if span.start.row == span.end.row {
+            return self.lines[span.start.row][span.start.col..span.end.col].to_owned();
+        }
+
+        let mut text = self.lines[span.start.row][span.start.col..].to_owned();
+        for line in &self.lines[span.start.row + 1..span.end.row] {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text.push('\n');
+        text.push_str(&self.lines[span.end.row][..span.end.col]);
+        text
+    }
+
+    fn apply(&mut self, edit: Edit) -> Change {
+        let change = match edit {
+            Edit::Insert { at, text } => {
+                let tail = self.lines[at.row].split_off(at.col);
+                let mut new_lines = text.split('\n');
+                self.lines[at.row].push_str(new_lines.next().unwrap());
+                let mut row = at.row;
+                for line in new_lines {
+                    row += 1;
+                    self.lines.insert(row, line.to_owned());
+                }
+                let new_end = Location {
+                    row,
+                    col: self.lines[row].len(),
+                };
+                self.lines[row].push_str(&tail);
+                Change {
+                    start: at,
+                    old_end: at,
+                    new_end,
+                }
+            }
+            Edit::Delete { at, text } => {
+                let end = end_of_insert(at, &text);
+                let tail = self.lines[end.row].split_off(end.col);
+                self.lines.drain(at.row + 1..=end.row);
+                self.lines[at.row].truncate(at.col);
+                self.lines[at.row].push_str(&tail);
+                Change {
+                    start: at,
+                    old_end: end,
+                    new_end: at,
+                }
+            }
+        };
+
+        // TODO: recalculating highlighting every time an edit happes is pretty expensive
+        // We should minimize the amount of recomputation and maybe allow for highlighting to be done
+        // in a more async manner?
+        self.highlight();
+        change
+    }
+
+    fn highlight(&mut self) {
+        self.highlight_generation += 1;
+        let syntax_set = &self.syntax_set;
+        let syntax = syntax_set
+            .find_syntax_by_name(&self.syntax)
+            .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+        generate_highlight_info(
+            &self.lines,
+            &mut self.highlight_info,
+            syntax,
+            syntax_set,
+            &self.theme_set.themes[&self.theme],
+        );
+    }
+
+    /// The symbols defined in the document, in the order they appear.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let syntax_set = &self.syntax_set;
+        let syntax = syntax_set
+            .find_syntax_by_name(&self.syntax)
+            .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+        let entity_name = Scope::new("entity.name").unwrap();
+        let mut parse_state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut symbols: Vec<Symbol> = vec![];
+
+        for (row, line) in self.lines.iter().enumerate() {
+            let ops = parse_state.parse_line(line, syntax_set);
+            let mut start = 0;
+            // The ops apply at byte offsets into the line. The text up to each one has the
+            // scopes left by the ones before it.
+            let ends = ops.iter().map(|(end, op)| (*end, Some(op)));
+            for (end, op) in ends.chain(std::iter::once((line.len(), None))) {
+                let scope = stack
+                    .as_slice()
+                    .iter()
+                    .rev()
+                    .find(|scope| entity_name.is_prefix_of(**scope));
+                if let (Some(scope), true) = (scope, end > start) {
+                    let text = &line[start..end];
+                    match symbols.last_mut() {
+                        // A name can be made of several tokens.
+                        Some(last)
+                            if last.location.row == row
+                                && last.location.col + last.name.len() == start =>
+                        {
+                            last.name.push_str(text)
+                        }
+                        _ => symbols.push(Symbol {
+                            name: text.to_owned(),
+                            kind: scope
+                                .build_string()
+                                .split('.')
+                                .nth(2)
+                                .unwrap_or("symbol")
+                                .to_owned(),
+                            location: Location { row, col: start },
+                        }),
+                    }
+                }
+                start = end;
+                if let Some(op) = op {
+                    stack.apply(op);
+                }
+            }
+        }
+
+        symbols.retain(|symbol| !symbol.name.trim().is_empty());
+        symbols
+    }
+
+    /// Name of the syntax the document is highlighted with, e.g. "Rust".
+    pub fn syntax_name(&self) -> &str {
+        &self.syntax
+    }
+
+    /// Highlights the document with the syntax called `name` rather than the detected one.
+    /// Returns false if there is no such syntax.
+    pub fn set_syntax(&mut self, name: &str) -> bool {
+        let syntax = match find_syntax(&self.syntax_set, name) {
+            Some(syntax) => syntax.name.clone(),
+            None => return false,
+        };
+        if syntax != self.syntax {
+            self.syntax = syntax;
+            self.highlight();
+        }
+        true
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Read-only documents turn down edits, though they can still be reloaded from disk.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+    }
+
+    /// Switches to the bundled theme called `theme`. Returns false if there is no such theme.
+    pub fn set_theme(&mut self, theme: &str) -> bool {
+        if !self.theme_set.themes.contains_key(theme) {
+            return false;
+        }
+        if self.theme != theme {
+            self.theme = theme.to_owned();
+            self.highlight();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_line_endings_round_trip() {
+        // Mostly LF: the stray '\r' stays part of its line, so saving changes nothing.
+        let bytes = b"a\r\nb\nc\n";
+        let document = Document::from_bytes("lf".to_owned(), bytes);
+        assert!(!document.is_modified());
+        assert!(!document.normalizes_line_endings());
+        assert_eq!(document.encoded(), bytes);
+
+        // Mostly CRLF: saving makes every break CRLF, so the document starts out modified.
+        let document = Document::from_bytes("crlf".to_owned(), b"a\r\nb\r\nc\n");
+        assert_eq!(document.lines(), ["a", "b", "c", ""]);
+        assert!(document.is_modified());
+        assert!(document.normalizes_line_endings());
+        assert_eq!(document.encoded(), b"a\r\nb\r\nc\r\n");
+    }
+
+    #[test]
+    fn saving_settles_mixed_line_endings() {
+        let path = std::env::temp_dir().join(format!("brewcode-mixed-{}", std::process::id()));
+        fs::write(&path, b"a\r\nb\r\nc\n").unwrap();
+        let mut document = Document::open(&path).unwrap();
+        assert!(document.is_modified());
+        document.save().unwrap();
+        assert!(!document.is_modified());
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\nb\r\nc\r\n");
+        let _ = fs::remove_file(&path);
+    }
+}
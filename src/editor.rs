@@ -1,43 +1,1838 @@
-use crate::buffer::Buffer;
-use crate::rectangle_brush::RectangleBrush;
-use wgpu_glyph::GlyphBrush;
+use crate::cli::{Args, Source};
+use crate::command::Command;
+use crate::config::{self, Config, ViewSettings};
+use crate::diff;
+use crate::document::{Change, Document, Location};
+use crate::encoding::LineEnding;
+use crate::explorer::{Explorer, ExplorerAction};
+use crate::file_index::FileIndex;
+#[cfg(unix)]
+use crate::ipc::{self, Client, OpenRequest};
+use crate::keymap::{self, Key, KeyPress, KeyResult, Keymap};
+use crate::layout::Rect;
+use crate::notification::{Notification, NOTIFICATION_HEIGHT};
+use crate::palette::{Palette, PaletteAnswer, PaletteItem};
+use crate::pane::{Axis, Direction, PaneTree};
+use crate::prompt::{Choice, Prompt, PromptAnswer};
+use crate::recovery::{self, Journal};
+use crate::renderer::Renderer;
+use crate::session::{self, Session, SessionDocument, ViewState};
+use crate::status_bar::{StatusBar, STATUS_BAR_HEIGHT};
+use crate::tab_bar::{Tab, TabAction, TabBar, TAB_BAR_HEIGHT};
+use crate::view::View;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, KeyboardInput, MouseButton},
+    event::{ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode},
+    event_loop::EventLoopProxy,
 };
 
+/// Sent to the event loop from background threads to wake it up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserEvent {
+    /// The file index found more files.
+    FilesIndexed,
+    /// Another instance asked to open files.
+    #[cfg(unix)]
+    OpenRequested,
+}
+
+/// What to do once the open prompt is answered.
+enum PromptPurpose {
+    Quit,
+    CloseDocument(usize),
+    SaveAs(usize),
+    Rename(usize),
+    DiskChanged(usize),
+    OverwriteNewer(usize),
+    OverwriteExisting(usize, PathBuf),
+    RenameOverwrite(usize, PathBuf),
+    Recover(usize, Vec<String>),
+    RecoverUntitled(String, Vec<String>),
+    CreateFile(PathBuf),
+    CreateFolder(PathBuf),
+    RenameEntry(PathBuf),
+    DeleteEntry(PathBuf),
+    GoToLine,
+}
+
+/// What picking an item in the open palette does.
+enum PalettePurpose {
+    /// Runs the command at the index of the picked item.
+    Commands(Vec<Command>),
+    /// Opens the file at the index of the picked item.
+    Files(FileIndex),
+    /// Moves the cursor to the location at the index of the picked item.
+    Symbols(Vec<Location>),
+}
+
 pub struct Editor {
-    buffers: Vec<Buffer>,
-    active_buffer: usize,
+    documents: Vec<Document>,
+    // The last view each document was shown with, so switching back to it in a pane keeps the
+    // cursor where it was. Kept in the same order as `documents`.
+    stashed_views: Vec<Option<View>>,
+    panes: PaneTree,
+    tab_bar: TabBar,
+    status_bar: StatusBar,
+    prompt: Option<(Prompt, PromptPurpose)>,
+    palette: Option<(Palette, PalettePurpose)>,
+    explorer: Explorer,
+    // Whether the explorer has the keyboard rather than the focused pane.
+    explorer_focused: bool,
+    should_quit: bool,
+    // Set while Save All on quit works through the modified documents one at a time.
+    quitting: bool,
+    // Set once saving the session on quit failed, so quitting again goes ahead without it.
+    session_failed: bool,
+    // The document to close once the prompts that saving it opened have saved it.
+    closing: Option<usize>,
+    notification: Option<Notification>,
+    // Documents with a recovery journal and the revision that was journaled.
+    journaled: HashMap<Journal, usize>,
+    // Journals left behind by an earlier session that the user hasn't been asked about yet,
+    // for open documents and for untitled documents by their journal id.
+    recoveries: Vec<(usize, Vec<String>)>,
+    untitled_recoveries: Vec<(String, Vec<String>)>,
+    // Journals of open files that the user hasn't restored or discarded yet, including those put
+    // off until later. They aren't overwritten, so the changes in them aren't lost.
+    pending_journals: HashSet<Journal>,
+    autosave_on_focus_loss: bool,
+    config: Config,
+    // Modification times of the config files, to reload them when they change.
+    config_modified: Vec<(PathBuf, Option<SystemTime>)>,
+    // Read instead of the user config file, from `--config`.
+    config_file: Option<PathBuf>,
+    keymap: Keymap,
+    // Set when the keymap used a key press, so the character it types is dropped.
+    swallow_char: bool,
+    // Width of a character per pixel of font size, measured from the font in use.
+    char_width: f32,
+    mouse_position: PhysicalPosition<i32>,
+    // Set when something on screen changed, so a frame needs drawing.
+    redraw: bool,
     size: PhysicalSize<u32>,
+    event_proxy: Option<EventLoopProxy<UserEvent>>,
+    #[cfg(unix)]
+    ipc_server: Option<ipc::Server>,
+    // Instances waiting for the documents at these paths to be closed.
+    #[cfg(unix)]
+    waiting_clients: Vec<(Vec<PathBuf>, Client)>,
+}
+
+/// Reads the input of the go to line prompt, given the cursor is on `row`: "12" is line 12,
+/// "12:5" is column 5 of it, and "+3" and "-3" are 3 lines below and above. Lines and columns
+/// count from 1 and columns are in characters. Returns the row and column counting from 0.
+fn parse_line_target(input: &str, row: usize) -> Option<(usize, usize)> {
+    let input = input.trim();
+    if let Some(lines) = input.strip_prefix('+') {
+        return Some((row.saturating_add(lines.parse().ok()?), 0));
+    }
+    if let Some(lines) = input.strip_prefix('-') {
+        return Some((row.saturating_sub(lines.parse().ok()?), 0));
+    }
+    let (line, col) = match input.split_once(':') {
+        Some((line, col)) => (line, col.parse::<usize>().ok()?),
+        None => (input, 1),
+    };
+    let line = line.parse::<usize>().ok()?;
+    Some((line.saturating_sub(1), col.saturating_sub(1)))
+}
+
+/// The parts of the window everything is drawn in.
+struct Regions {
+    explorer: Rect,
+    tabs: Rect,
+    panes: Rect,
+    notification: Rect,
+    status: Rect,
+}
+
+/// Splits the window into the status bar along the bottom, above it a strip for the notification
+/// when there is one, the explorer along the left, `explorer_width` wide, and the tab strip and
+/// the pane area in the rest.
+fn layout(size: PhysicalSize<u32>, notification: bool, explorer_width: f32) -> Regions {
+    let window = Rect::new(0.0, 0.0, size.width as f32, size.height as f32);
+    let (rest, status) = window.split_bottom(STATUS_BAR_HEIGHT);
+    let notification_height = if notification {
+        NOTIFICATION_HEIGHT
+    } else {
+        0.0
+    };
+    let (rest, notification) = rest.split_bottom(notification_height);
+    let (explorer, rest) = rest.split_left(explorer_width);
+    let (tabs, panes) = rest.split_top(TAB_BAR_HEIGHT);
+    Regions {
+        explorer,
+        tabs,
+        panes,
+        notification,
+        status,
+    }
+}
+
+fn config_modified_times(user_file: Option<&Path>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    config::sources(user_file)
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+/// Where the item at `index` ends up after the item at `from` is moved to `to`.
+fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && to >= index {
+        index - 1
+    } else if from > index && to <= index {
+        index + 1
+    } else {
+        index
+    }
 }
 
 impl Editor {
-    pub fn new(size: PhysicalSize<u32>, file_name: String) -> Self {
-        Self {
-            buffers: vec![Buffer::new(size, file_name)],
-            active_buffer: 0,
+    /// Opens the files named in `args`. Without any, or with `--restore`, the session last saved
+    /// in the current directory is brought back first and the files are opened alongside it.
+    pub fn new(size: PhysicalSize<u32>, args: &Args) -> Self {
+        Self::open(size, args, false)
+    }
+
+    /// Opens only what `args` names, with the default config or just the one given with
+    /// `--config`. Nothing is read from the saved session, the recovery journals or the user and
+    /// project config, so the result doesn't depend on the directory or machine it runs on.
+    pub fn hermetic(size: PhysicalSize<u32>, args: &Args) -> Self {
+        Self::open(size, args, true)
+    }
+
+    fn open(size: PhysicalSize<u32>, args: &Args, hermetic: bool) -> Self {
+        let mut documents = vec![];
+        let mut stashed_states = vec![];
+        let mut errors = vec![];
+        let mut restored_layout = None;
+
+        // Starting without any files brings back the last session in this directory. Not while
+        // another instance runs though, which may have the same session open and be journaling
+        // the same files.
+        let restore = !hermetic && (args.restore || args.files.is_empty());
+        #[cfg(unix)]
+        let session_in_use = restore && ipc::is_running();
+        #[cfg(not(unix))]
+        let session_in_use = false;
+        let restore = restore && !session_in_use;
+        let saved_session = if restore { session::load() } else { None };
+        if let Some(mut session) = saved_session {
+            // Files that were deleted since are dropped, so session indices need remapping.
+            let mut indices = vec![];
+            for saved in session.documents {
+                if !saved.path.exists() {
+                    indices.push(None);
+                    continue;
+                }
+                match Document::open(&saved.path) {
+                    Ok(document) => {
+                        indices.push(Some(documents.len()));
+                        documents.push(document);
+                        stashed_states.push(saved.stashed_view);
+                    }
+                    Err(error) => {
+                        indices.push(None);
+                        errors.push(format!(
+                            "Failed to open {}: {}",
+                            saved.path.display(),
+                            error
+                        ));
+                    }
+                }
+            }
+
+            if !documents.is_empty() {
+                for (document, view) in session.layout.panes_mut() {
+                    match indices.get(*document).copied().flatten() {
+                        Some(index) => *document = index,
+                        None => {
+                            *document = 0;
+                            *view = ViewState::default();
+                        }
+                    }
+                }
+                restored_layout = Some((session.layout, session.focused));
+            }
+        }
+
+        let first_opened = documents.len();
+        // Where to put the cursor in the documents opened from the command line.
+        let mut positions = vec![];
+        for file in &args.files {
+            let opened = match &file.source {
+                Source::Path(path) => Document::open(path)
+                    .map_err(|error| format!("Failed to open {}: {}", path.display(), error)),
+                Source::Stdin => {
+                    let mut bytes = vec![];
+                    io::stdin()
+                        .read_to_end(&mut bytes)
+                        .map(|_| Document::from_bytes("stdin".to_owned(), &bytes))
+                        .map_err(|error| format!("Failed to read standard input: {}", error))
+                }
+            };
+            match opened {
+                Ok(mut document) => {
+                    document.set_readonly(args.readonly);
+                    if let Some(syntax) = &args.syntax {
+                        document.set_syntax(syntax);
+                    }
+                    if let Some(position) = file.position {
+                        positions.push((documents.len(), position));
+                    }
+                    documents.push(document);
+                    stashed_states.push(None);
+                }
+                Err(message) => errors.push(message),
+            }
+        }
+        // Without any files to show, start with a scratch document.
+        if documents.is_empty() {
+            documents.push(Document::untitled());
+            stashed_states.push(None);
+        }
+
+        let mut recoveries = vec![];
+        if !hermetic {
+            for (index, document) in documents.iter().enumerate() {
+                if let Some(path) = document.path() {
+                    match recovery::read(path) {
+                        Some(lines) if lines != document.lines() => recoveries.push((index, lines)),
+                        Some(_) => recovery::remove(&Journal::File(path.to_owned())),
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        let (config, config_errors) = if hermetic {
+            Config::load_only(args.config.as_deref())
+        } else {
+            Config::load(args.config.as_deref())
+        };
+        errors.extend(config_errors);
+
+        let notification = if errors.is_empty() {
+            None
+        } else {
+            Some(Notification::error(errors.join("; ")))
+        };
+        let explorer = Explorer::new(std::env::current_dir().unwrap_or_default());
+        let regions = layout(size, notification.is_some(), 0.0);
+        let pane_bounds = regions.panes;
+        let panes = match &restored_layout {
+            Some((layout, focused)) => PaneTree::from_layout(pane_bounds, layout, *focused),
+            None => PaneTree::new(pane_bounds, 0),
+        };
+        let stashed_views = stashed_states
+            .iter()
+            .map(|state| {
+                state
+                    .as_ref()
+                    .map(|state| View::from_state(pane_bounds, state))
+            })
+            .collect();
+        let pending_journals = recoveries
+            .iter()
+            .filter_map(|(index, _)| documents[*index].journal())
+            .collect();
+        let mut editor = Self {
+            stashed_views,
+            documents,
+            panes,
+            tab_bar: TabBar::new(regions.tabs),
+            status_bar: StatusBar::new(regions.status),
+            prompt: None,
+            palette: None,
+            explorer,
+            explorer_focused: false,
+            should_quit: false,
+            quitting: false,
+            session_failed: false,
+            closing: None,
+            notification,
+            journaled: HashMap::new(),
+            pending_journals,
+            recoveries,
+            untitled_recoveries: if hermetic {
+                vec![]
+            } else {
+                recovery::read_untitled()
+            },
+            autosave_on_focus_loss: config.autosave_on_focus_loss,
+            keymap: Keymap::new(&config.keys),
+            swallow_char: false,
+            config,
+            config_modified: if hermetic {
+                vec![]
+            } else {
+                config_modified_times(args.config.as_deref())
+            },
+            config_file: args.config.clone(),
+            char_width: ViewSettings::default().char_width / ViewSettings::default().font_size,
+            mouse_position: PhysicalPosition::new(0, 0),
+            redraw: false,
             size,
+            event_proxy: None,
+            #[cfg(unix)]
+            ipc_server: None,
+            #[cfg(unix)]
+            waiting_clients: vec![],
+        };
+        editor.request_redraw();
+        editor.apply_config();
+        editor.clamp_views();
+        // Files named on the command line take the focused pane over from the restored session.
+        if restored_layout.is_some() && first_opened < editor.documents.len() {
+            editor.show_document(first_opened);
+        }
+        for (index, position) in positions {
+            editor.place_cursor(index, position);
+        }
+        for index in 0..editor.documents.len() {
+            editor.warn_about_line_endings(index);
+        }
+        if session_in_use {
+            let message = "Another brewcode window is open, so the last session wasn't restored";
+            editor.set_status_message(message.to_owned());
+        }
+        editor.offer_next_recovery();
+        editor
+    }
+
+    /// Puts the cursor of the document at `index` on a line and column counting from 1, as given
+    /// on the command line.
+    fn place_cursor(&mut self, index: usize, (line, col): (usize, Option<usize>)) {
+        let document = &self.documents[index];
+        let location =
+            document.location_at(line.saturating_sub(1), col.unwrap_or(1).saturating_sub(1));
+        let pane = self.panes.focused_mut();
+        if pane.document == index {
+            pane.view.go_to(document, location);
+        } else {
+            let mut view = View::new(pane.view.bounds());
+            view.go_to(document, location);
+            self.stashed_views[index] = Some(view);
+        }
+    }
+
+    /// Lets background work wake the event loop up when it has results.
+    pub fn set_event_proxy(&mut self, proxy: EventLoopProxy<UserEvent>) {
+        self.event_proxy = Some(proxy);
+    }
+
+    pub fn handle_user_event(&mut self, event: UserEvent) {
+        match event {
+            UserEvent::FilesIndexed => {
+                if self.poll_file_index() {
+                    self.request_redraw();
+                }
+            }
+            #[cfg(unix)]
+            UserEvent::OpenRequested => {
+                let requests = match self.ipc_server.as_mut() {
+                    Some(server) => server.poll(),
+                    None => return,
+                };
+                for request in requests {
+                    self.handle_open_request(request);
+                }
+                self.request_redraw();
+            }
+        }
+    }
+
+    fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+
+    /// Whether anything on screen changed since the last call. Frames are only drawn then, and
+    /// always in full.
+    pub fn take_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.redraw)
+    }
+
+    /// Starts taking requests to open files from other instances, unless one of them already
+    /// does.
+    #[cfg(unix)]
+    pub fn start_ipc_server(&mut self) {
+        let proxy = self.event_proxy.clone();
+        let server = ipc::Server::start(move || {
+            if let Some(proxy) = &proxy {
+                let _ = proxy.send_event(UserEvent::OpenRequested);
+            }
+        });
+        match server {
+            Ok(server) => self.ipc_server = Some(server),
+            // This is a second window opened on purpose.
+            Err(error) if error.kind() == io::ErrorKind::AddrInUse => {}
+            Err(error) => self.report_error(format!(
+                "Failed to listen for files opened from the command line: {}",
+                error
+            )),
+        }
+    }
+
+    #[cfg(unix)]
+    fn handle_open_request(&mut self, mut request: OpenRequest) {
+        let mut paths = vec![];
+        for file in &request.files {
+            let path = match &file.source {
+                Source::Path(path) => path,
+                Source::Stdin => continue,
+            };
+            let index = match self.open_file(path) {
+                Some(index) => index,
+                None => continue,
+            };
+            let document = &mut self.documents[index];
+            if request.readonly {
+                document.set_readonly(true);
+            }
+            if let Some(syntax) = &request.syntax {
+                document.set_syntax(syntax);
+            }
+            paths.extend(document.path().map(Path::to_owned));
+            if let Some(position) = file.position {
+                self.place_cursor(index, position);
+            }
+        }
+        self.configure_views();
+
+        request.client.send_opened();
+        if request.wait {
+            self.waiting_clients.push((paths, request.client));
+            self.release_waiting_clients();
+        }
+    }
+
+    /// Lets the instances waiting on documents that are all closed now go.
+    #[cfg(unix)]
+    fn release_waiting_clients(&mut self) {
+        let documents = &self.documents;
+        let is_open = |path: &PathBuf| {
+            documents
+                .iter()
+                .any(|document| document.path() == Some(path.as_path()))
+        };
+        let (closed, waiting): (Vec<_>, Vec<_>) = self
+            .waiting_clients
+            .drain(..)
+            .partition(|(paths, _)| !paths.iter().any(is_open));
+        self.waiting_clients = waiting;
+        for (_, client) in closed {
+            client.send_closed();
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Reloads the config if any of its files changed. Returns whether it did.
+    pub fn check_config_changes(&mut self) -> bool {
+        let modified = config_modified_times(self.config_file.as_deref());
+        if modified == self.config_modified {
+            return false;
+        }
+        self.config_modified = modified;
+
+        let (config, errors) = Config::load(self.config_file.as_deref());
+        self.config = config;
+        self.autosave_on_focus_loss = self.config.autosave_on_focus_loss;
+        self.apply_config();
+        self.request_redraw();
+        if errors.is_empty() {
+            self.set_status_message("Reloaded config".to_owned());
+        } else {
+            self.notify(Notification::error(errors.join("; ")));
+        }
+        true
+    }
+
+    /// Passes the config on to the documents and views.
+    fn apply_config(&mut self) {
+        let theme = self.config.theme.clone();
+        let mut unknown_theme = false;
+        for document in self.documents.iter_mut() {
+            unknown_theme |= !document.set_theme(&theme);
+        }
+        if unknown_theme {
+            self.notify(Notification::error(format!("Unknown theme `{}`", theme)));
+        }
+        self.keymap = Keymap::new(&self.config.keys);
+        self.configure_views();
+    }
+
+    /// Sets how wide a character is per pixel of font size, for when the font changes.
+    pub fn set_char_width(&mut self, char_width: f32) {
+        self.char_width = char_width;
+        self.configure_views();
+    }
+
+    pub fn report_error(&mut self, message: String) {
+        self.notify(Notification::error(message));
+    }
+
+    fn view_settings(&self, document: &Document) -> ViewSettings {
+        ViewSettings {
+            font_size: self.config.font_size,
+            padding: self.config.padding,
+            char_width: self.char_width * self.config.font_size,
+            colors: self.config.colors.clone(),
+            language: self.config.language(document.syntax_name()),
+        }
+    }
+
+    /// Gives every view the settings for the language of its document. Needed whenever the
+    /// config changes or a view starts showing another document.
+    fn configure_views(&mut self) {
+        let settings: Vec<ViewSettings> = self
+            .documents
+            .iter()
+            .map(|document| self.view_settings(document))
+            .collect();
+        for pane in self.panes.iter_mut() {
+            pane.view.set_settings(settings[pane.document].clone());
+        }
+        for (view, settings) in self.stashed_views.iter_mut().zip(settings) {
+            if let Some(view) = view {
+                view.set_settings(settings);
+            }
+        }
+    }
+
+    /// Pulls every view back inside the document it shows.
+    fn clamp_views(&mut self) {
+        let documents = &self.documents;
+        for pane in self.panes.iter_mut() {
+            pane.view.clamp_to(&documents[pane.document]);
+        }
+        for (view, document) in self.stashed_views.iter_mut().zip(documents) {
+            if let Some(view) = view {
+                view.clamp_to(document);
+            }
+        }
+    }
+
+    /// What is open right now, to bring back the next time the editor starts here. Untitled
+    /// documents can't be reopened, so panes showing them fall back to the first document.
+    fn session(&self) -> Option<Session> {
+        let mut indices = vec![];
+        let mut documents = vec![];
+        for (document, stashed_view) in self.documents.iter().zip(&self.stashed_views) {
+            match document.path() {
+                Some(path) => {
+                    indices.push(Some(documents.len()));
+                    documents.push(SessionDocument {
+                        path: path.to_owned(),
+                        stashed_view: stashed_view.as_ref().map(View::state),
+                    });
+                }
+                None => indices.push(None),
+            }
+        }
+        if documents.is_empty() {
+            return None;
+        }
+
+        let (mut layout, focused) = self.panes.to_layout();
+        for (document, view) in layout.panes_mut() {
+            match indices[*document] {
+                Some(index) => *document = index,
+                None => {
+                    *document = 0;
+                    *view = ViewState::default();
+                }
+            }
+        }
+        Some(Session {
+            documents,
+            layout,
+            focused,
+        })
+    }
+
+    fn active_document(&self) -> usize {
+        self.panes.focused().document
+    }
+
+    pub fn save(&mut self) {
+        let active = self.active_document();
+        self.save_document(active);
+    }
+
+    /// Switches the line endings the focused document is saved with.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        let active = self.active_document();
+        self.documents[active].set_line_ending(line_ending);
+    }
+
+    /// Asks for a new path for the focused document and writes it there.
+    pub fn save_as(&mut self) {
+        let active = self.active_document();
+        self.prompt_for_path("Save as:", active, PromptPurpose::SaveAs(active));
+    }
+
+    /// Asks for a new path for the focused document and moves its file there.
+    pub fn rename(&mut self) {
+        let active = self.active_document();
+        // An untitled document has no file to move yet.
+        if self.documents[active].is_untitled() {
+            self.prompt_for_path("Save as:", active, PromptPurpose::SaveAs(active));
+        } else {
+            self.prompt_for_path("Rename to:", active, PromptPurpose::Rename(active));
+        }
+    }
+
+    /// Opens a prompt for a path, filled in with the current path of the document at `index`.
+    fn prompt_for_path(&mut self, title: &str, index: usize, purpose: PromptPurpose) {
+        let initial = self.documents[index]
+            .path()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let prompt = Prompt::text_input(title.to_owned(), initial);
+        self.prompt = Some((prompt, purpose));
+    }
+
+    /// Saves the document at `index`, reporting failure in a notification. Untitled documents
+    /// ask for a path instead, and files another program changed ask before being overwritten.
+    /// Returns whether the document was written.
+    fn save_document(&mut self, index: usize) -> bool {
+        if self.documents[index].is_untitled() {
+            self.prompt_for_path("Save as:", index, PromptPurpose::SaveAs(index));
+            return false;
+        }
+
+        if self.documents[index].is_newer_on_disk() {
+            let prompt = Prompt::choose(
+                format!(
+                    "{} was changed on disk. Overwrite it?",
+                    self.documents[index].title()
+                ),
+                vec![],
+                vec![
+                    Choice {
+                        label: "Overwrite",
+                        shortcut: 'o',
+                    },
+                    Choice {
+                        label: "Cancel",
+                        shortcut: 'c',
+                    },
+                ],
+            );
+            self.prompt = Some((prompt, PromptPurpose::OverwriteNewer(index)));
+            return false;
+        }
+
+        self.write_document(index)
+    }
+
+    /// Writes the document at `index` to `path`, asking first if that would replace another file.
+    fn save_document_as(&mut self, index: usize, path: PathBuf) {
+        if self.replaces_other_file(index, &path) {
+            let purpose = PromptPurpose::OverwriteExisting(index, path.clone());
+            self.confirm_overwrite(&path, purpose);
+            return;
+        }
+        self.write_document_as(index, path);
+    }
+
+    /// Whether `path` is a file other than the one of the document at `index`.
+    fn replaces_other_file(&self, index: usize, path: &Path) -> bool {
+        path.exists() && self.documents[index].path() != Some(path)
+    }
+
+    /// Asks whether to replace the file at `path`, answering for `purpose`.
+    fn confirm_overwrite(&mut self, path: &Path, purpose: PromptPurpose) {
+        let prompt = Prompt::choose(
+            format!("{} already exists. Overwrite it?", path.display()),
+            vec![],
+            vec![
+                Choice {
+                    label: "Overwrite",
+                    shortcut: 'o',
+                },
+                Choice {
+                    label: "Cancel",
+                    shortcut: 'c',
+                },
+            ],
+        );
+        self.prompt = Some((prompt, purpose));
+    }
+
+    /// Moves the file of the document at `index` to `path`, asking first if that would replace
+    /// another file.
+    fn rename_document(&mut self, index: usize, path: PathBuf) {
+        if self.replaces_other_file(index, &path) {
+            let purpose = PromptPurpose::RenameOverwrite(index, path.clone());
+            self.confirm_overwrite(&path, purpose);
+            return;
+        }
+        self.move_document(index, path);
+    }
+
+    fn move_document(&mut self, index: usize, path: PathBuf) {
+        let document = &mut self.documents[index];
+        if let Err(error) = document.rename(path.clone()) {
+            let message = format!(
+                "Failed to rename {} to {}: {}",
+                document.title(),
+                path.display(),
+                error
+            );
+            self.notify(Notification::error(message));
+        }
+    }
+
+    fn write_document_as(&mut self, index: usize, path: PathBuf) {
+        if let Err(error) = self.documents[index].save_as(path.clone()) {
+            let message = format!("Failed to save {}: {}", path.display(), error);
+            self.notify(Notification::error(message));
+            self.quitting = false;
+        }
+    }
+
+    fn write_document(&mut self, index: usize) -> bool {
+        let document = &mut self.documents[index];
+        match document.save() {
+            Ok(()) => {
+                let message = format!("Saved {}", document.title());
+                self.set_status_message(message);
+                true
+            }
+            Err(error) => {
+                let message = format!("Failed to save {}: {}", document.title(), error);
+                self.notify(Notification::error(message));
+                false
+            }
+        }
+    }
+
+    /// Says so when the document at `index` was read with line endings that saving will change,
+    /// which is why it shows as modified.
+    fn warn_about_line_endings(&mut self, index: usize) {
+        let document = &self.documents[index];
+        if document.normalizes_line_endings() {
+            let message = format!(
+                "{} has mixed line endings, saving makes them all {}",
+                document.title(),
+                document.format().line_ending
+            );
+            self.set_status_message(message);
+        }
+    }
+
+    /// Looks for files that other programs changed. Documents without unsaved changes are
+    /// reloaded, for the others the user picks what to do.
+    pub fn check_disk_changes(&mut self) {
+        let mut changed = false;
+        for index in 0..self.documents.len() {
+            if !self.documents[index].has_unseen_disk_change() {
+                continue;
+            }
+
+            if !self.documents[index].is_modified() {
+                self.reload_document(index);
+                changed = true;
+            } else if self.prompt.is_none() {
+                let document = &mut self.documents[index];
+                document.mark_disk_change_seen();
+                let prompt = Prompt::choose(
+                    format!("{} was changed on disk.", document.title()),
+                    vec!["Reload it and lose your unsaved changes?".to_owned()],
+                    vec![
+                        Choice {
+                            label: "Reload",
+                            shortcut: 'r',
+                        },
+                        Choice {
+                            label: "Keep Mine",
+                            shortcut: 'k',
+                        },
+                        Choice {
+                            label: "Diff",
+                            shortcut: 'd',
+                        },
+                    ],
+                );
+                self.prompt = Some((prompt, PromptPurpose::DiskChanged(index)));
+                changed = true;
+            }
+        }
+        if changed {
+            self.request_redraw();
+        }
+    }
+
+    fn reload_document(&mut self, index: usize) {
+        if let Err(error) = self.documents[index].reload() {
+            let message = format!(
+                "Failed to reload {}: {}",
+                self.documents[index].title(),
+                error
+            );
+            self.notify(Notification::error(message));
+            return;
+        }
+        self.warn_about_line_endings(index);
+
+        let document = &self.documents[index];
+        for pane in self.panes.iter_mut() {
+            if pane.document == index {
+                pane.view.clamp_to(document);
+            }
+        }
+        if let Some(view) = self.stashed_views[index].as_mut() {
+            view.clamp_to(document);
+        }
+    }
+
+    /// Opens a new tab comparing the file on disk to the unsaved text of the document at `index`.
+    fn show_disk_diff(&mut self, index: usize) {
+        let document = &self.documents[index];
+        let disk_lines = match document.read_disk_lines() {
+            Ok(lines) => lines,
+            Err(error) => {
+                let message = format!("Failed to read {}: {}", document.title(), error);
+                self.notify(Notification::error(message));
+                return;
+            }
+        };
+
+        let title = document.title();
+        let text = diff::unified(
+            &format!("{} (on disk)", title),
+            &disk_lines,
+            &format!("{} (unsaved)", title),
+            document.lines(),
+        );
+        let mut diff_document = Document::scratch(format!("{}.diff", title), &text);
+        diff_document.set_theme(&self.config.theme);
+        self.documents.push(diff_document);
+        self.stashed_views.push(None);
+        self.show_document(self.documents.len() - 1);
+    }
+
+    fn notify(&mut self, notification: Notification) {
+        self.notification = Some(notification);
+        self.update_size(self.size);
+    }
+
+    /// Where everything is in the window right now.
+    fn regions(&self) -> Regions {
+        layout(
+            self.size,
+            self.notification.is_some(),
+            self.explorer.width(),
+        )
+    }
+
+    fn dismiss_notification(&mut self) {
+        if self.notification.take().is_some() {
+            self.update_size(self.size);
+        }
+    }
+
+    /// The window title: the focused document, whether it has unsaved changes, and the app name.
+    pub fn window_title(&self) -> String {
+        let document = &self.documents[self.active_document()];
+        let marker = if document.is_modified() {
+            " \u{2022}"
+        } else {
+            ""
+        };
+        format!("{}{} - brewcode", document.title(), marker)
+    }
+
+    /// Asks to quit, first checking with the user if anything is unsaved.
+    /// Check `should_quit` afterwards to see whether the editor can exit.
+    pub fn request_quit(&mut self) {
+        self.request_redraw();
+        let dirty: Vec<String> = self
+            .documents
+            .iter()
+            .filter(|document| document.is_modified())
+            .map(|document| format!("  {}", document.title()))
+            .collect();
+
+        if dirty.is_empty() {
+            self.quit();
+            return;
+        }
+
+        let title = if dirty.len() == 1 {
+            "1 file has unsaved changes:".to_owned()
+        } else {
+            format!("{} files have unsaved changes:", dirty.len())
+        };
+        let prompt = Prompt::choose(
+            title,
+            dirty,
+            vec![
+                Choice {
+                    label: "Save All",
+                    shortcut: 's',
+                },
+                Choice {
+                    label: "Discard",
+                    shortcut: 'd',
+                },
+                Choice {
+                    label: "Cancel",
+                    shortcut: 'c',
+                },
+            ],
+        );
+        self.prompt = Some((prompt, PromptPurpose::Quit));
+    }
+
+    /// Saves the modified documents one at a time after Save All on quit, and quits once none
+    /// are left. Stops to wait whenever a document needs a prompt answered first, and stays
+    /// open if one fails to save so the changes aren't lost.
+    /// Closes the document whose saving went through prompts, if they saved it.
+    fn continue_close(&mut self) {
+        if let Some(index) = self.closing.take() {
+            if !self.documents[index].is_modified() {
+                self.remove_document(index);
+            }
+        }
+    }
+
+    fn continue_quit(&mut self) {
+        if !self.quitting {
+            return;
+        }
+        while let Some(index) = self.documents.iter().position(Document::is_modified) {
+            if !self.save_document(index) {
+                // Without a prompt waiting on the user, saving failed.
+                if self.prompt.is_none() {
+                    self.quitting = false;
+                }
+                return;
+            }
+        }
+        self.quitting = false;
+        self.quit();
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Exits cleanly. Whatever is still unsaved was deliberately left that way, so it doesn't
+    /// need recovering. If the session can't be saved the editor stays open to say so, and
+    /// quitting again goes ahead without it.
+    fn quit(&mut self) {
+        if !self.session_failed {
+            let saved = match self.session() {
+                Some(session) => session::save(&session),
+                // Nothing to bring back, so neither should an older session be.
+                None => session::remove(),
+            };
+            if let Err(error) = saved {
+                self.session_failed = true;
+                self.quitting = false;
+                let message = format!(
+                    "Failed to save session: {}. Quit again to quit without it.",
+                    error
+                );
+                self.notify(Notification::error(message));
+                return;
+            }
+        }
+
+        for journal in self.journaled.keys() {
+            recovery::remove(journal);
+        }
+        self.journaled.clear();
+        recovery::release_owner();
+        // Stop taking files, and let the instances waiting on this one go.
+        #[cfg(unix)]
+        {
+            self.ipc_server = None;
+            self.waiting_clients.clear();
         }
+        self.should_quit = true;
     }
 
-    pub fn save(&self) {
-        self.buffers[self.active_buffer].save();
+    /// Journals the text of modified documents so it can be recovered if the editor dies before
+    /// they are saved. Journals of documents that are no longer modified are removed.
+    pub fn journal_unsaved(&mut self) {
+        for document in self.documents.iter_mut() {
+            if document.is_modified() && document.journal().is_none() {
+                document.set_untitled_journal(recovery::new_untitled_id());
+            }
+        }
+
+        let mut modified = HashMap::new();
+        for document in &self.documents {
+            if let (Some(journal), true) = (document.journal(), document.is_modified()) {
+                if !self.pending_journals.contains(&journal) {
+                    modified.insert(journal, document);
+                }
+            }
+        }
+
+        let stale: Vec<Journal> = self
+            .journaled
+            .keys()
+            .filter(|journal| !modified.contains_key(*journal))
+            .cloned()
+            .collect();
+        for journal in stale {
+            recovery::remove(&journal);
+            self.journaled.remove(&journal);
+        }
+
+        let mut failed = None;
+        for (journal, document) in modified {
+            if self.journaled.get(&journal) == Some(&document.revision()) {
+                continue;
+            }
+            if let Err(error) = recovery::write(&journal, document.lines()) {
+                failed = Some(format!(
+                    "Failed to write recovery file for {}: {}",
+                    document.title(),
+                    error
+                ));
+            }
+            // Even when writing failed, so the error isn't repeated every time.
+            self.journaled.insert(journal, document.revision());
+        }
+        if let Some(message) = failed {
+            self.notify(Notification::error(message));
+        }
+    }
+
+    /// Asks about the next journal left behind by an earlier session, if there is one and
+    /// nothing else is being asked.
+    fn offer_next_recovery(&mut self) {
+        if self.prompt.is_some() {
+            return;
+        }
+
+        let (title, purpose) = if !self.recoveries.is_empty() {
+            let (index, lines) = self.recoveries.remove(0);
+            let title = format!(
+                "Found unsaved changes to {} from an earlier session.",
+                self.documents[index].title()
+            );
+            (title, PromptPurpose::Recover(index, lines))
+        } else if !self.untitled_recoveries.is_empty() {
+            let (id, lines) = self.untitled_recoveries.remove(0);
+            let title =
+                "Found unsaved changes to an untitled document from an earlier session.".to_owned();
+            (title, PromptPurpose::RecoverUntitled(id, lines))
+        } else {
+            return;
+        };
+        let prompt = Prompt::choose(
+            title,
+            vec!["Restore them?".to_owned()],
+            vec![
+                Choice {
+                    label: "Restore",
+                    shortcut: 'r',
+                },
+                Choice {
+                    label: "Discard",
+                    shortcut: 'd',
+                },
+                Choice {
+                    label: "Later",
+                    shortcut: 'l',
+                },
+            ],
+        );
+        self.prompt = Some((prompt, purpose));
+    }
+
+    fn restore_recovered(&mut self, index: usize, lines: Vec<String>) {
+        let document = &mut self.documents[index];
+        document.replace_all(&lines.join("\n"));
+        let document = &self.documents[index];
+        for pane in self.panes.iter_mut() {
+            if pane.document == index {
+                pane.view.clamp_to(document);
+            }
+        }
+        if let Some(view) = self.stashed_views[index].as_mut() {
+            view.clamp_to(document);
+        }
+    }
+
+    /// Opens the text journaled for an untitled document as a new one, which keeps writing to
+    /// the same journal.
+    fn restore_untitled(&mut self, id: String, lines: Vec<String>) {
+        let mut document = Document::untitled();
+        document.set_theme(&self.config.theme);
+        document.replace_all(&lines.join("\n"));
+        document.set_untitled_journal(id.clone());
+        self.journaled
+            .insert(Journal::Untitled(id), document.revision());
+        self.documents.push(document);
+        self.stashed_views.push(None);
+        self.show_document(self.documents.len() - 1);
+    }
+
+    /// With auto-save on focus loss, modified documents are saved whenever the window loses
+    /// focus. Untitled documents and files changed on disk are left alone rather than prompting.
+    pub fn handle_focus_lost(&mut self) {
+        if !self.autosave_on_focus_loss || self.prompt.is_some() {
+            return;
+        }
+        self.request_redraw();
+        for index in 0..self.documents.len() {
+            let document = &self.documents[index];
+            if document.is_modified() && !document.is_untitled() && !document.is_newer_on_disk() {
+                self.write_document(index);
+            }
+        }
+    }
+
+    pub fn toggle_autosave_on_focus_loss(&mut self) {
+        self.autosave_on_focus_loss = !self.autosave_on_focus_loss;
+        let message = if self.autosave_on_focus_loss {
+            "Auto-save on focus loss enabled"
+        } else {
+            "Auto-save on focus loss disabled"
+        };
+        self.set_status_message(message.to_owned());
+    }
+
+    fn handle_prompt_answer(&mut self, purpose: PromptPurpose, answer: PromptAnswer) {
+        match (purpose, answer) {
+            (PromptPurpose::Quit, PromptAnswer::Choice(0)) => self.quitting = true,
+            (PromptPurpose::Quit, PromptAnswer::Choice(1)) => self.quit(),
+            (PromptPurpose::CloseDocument(index), PromptAnswer::Choice(0)) => {
+                // Keep the document open if it couldn't be saved.
+                if self.save_document(index) {
+                    self.remove_document(index);
+                } else if self.prompt.is_some() {
+                    self.closing = Some(index);
+                }
+            }
+            (PromptPurpose::CloseDocument(index), PromptAnswer::Choice(1)) => {
+                self.remove_document(index)
+            }
+            (PromptPurpose::SaveAs(index), PromptAnswer::Text(path)) if !path.is_empty() => {
+                self.save_document_as(index, PathBuf::from(path))
+            }
+            (PromptPurpose::OverwriteExisting(index, path), PromptAnswer::Choice(0)) => {
+                self.write_document_as(index, path)
+            }
+            (PromptPurpose::DiskChanged(index), PromptAnswer::Choice(0)) => {
+                self.reload_document(index)
+            }
+            (PromptPurpose::DiskChanged(index), PromptAnswer::Choice(2)) => {
+                self.show_disk_diff(index)
+            }
+            (PromptPurpose::OverwriteNewer(index), PromptAnswer::Choice(0)) => {
+                let written = self.write_document(index);
+                self.quitting &= written;
+            }
+            // Backing out of saving a document while quitting or closing it means keeping it.
+            (PromptPurpose::SaveAs(_), _)
+            | (PromptPurpose::OverwriteExisting(..), _)
+            | (PromptPurpose::OverwriteNewer(_), _) => {
+                self.quitting = false;
+                self.closing = None;
+            }
+            (PromptPurpose::Rename(index), PromptAnswer::Text(path)) if !path.is_empty() => {
+                self.rename_document(index, PathBuf::from(path))
+            }
+            (PromptPurpose::RenameOverwrite(index, path), PromptAnswer::Choice(0)) => {
+                self.move_document(index, path)
+            }
+            (PromptPurpose::Recover(index, lines), PromptAnswer::Choice(0)) => {
+                if let Some(journal) = self.documents[index].journal() {
+                    self.pending_journals.remove(&journal);
+                }
+                self.restore_recovered(index, lines)
+            }
+            (PromptPurpose::CreateFile(dir), PromptAnswer::Text(name)) if !name.is_empty() => {
+                self.create_entry(&dir, &name, false)
+            }
+            (PromptPurpose::CreateFolder(dir), PromptAnswer::Text(name)) if !name.is_empty() => {
+                self.create_entry(&dir, &name, true)
+            }
+            (PromptPurpose::RenameEntry(path), PromptAnswer::Text(name)) if !name.is_empty() => {
+                self.rename_entry(&path, &name)
+            }
+            (PromptPurpose::DeleteEntry(path), PromptAnswer::Choice(0)) => self.delete_entry(&path),
+            (PromptPurpose::GoToLine, PromptAnswer::Text(input)) if !input.trim().is_empty() => {
+                self.go_to_line(&input)
+            }
+            (PromptPurpose::Recover(index, _), PromptAnswer::Choice(1)) => {
+                if let Some(journal) = self.documents[index].journal() {
+                    self.pending_journals.remove(&journal);
+                    recovery::remove(&journal);
+                }
+            }
+            (PromptPurpose::RecoverUntitled(id, lines), PromptAnswer::Choice(0)) => {
+                self.restore_untitled(id, lines)
+            }
+            (PromptPurpose::RecoverUntitled(id, _), PromptAnswer::Choice(1)) => {
+                recovery::remove(&Journal::Untitled(id))
+            }
+            _ => {}
+        }
+        // Saving under a new name can change the language of a document.
+        self.configure_views();
+        if self.prompt.is_none() {
+            self.continue_close();
+            self.continue_quit();
+        }
+        self.offer_next_recovery();
     }
 
     pub fn update_size(&mut self, size: PhysicalSize<u32>) {
         self.size = size;
-        for buffer in &mut self.buffers {
-            buffer.update_size(size);
+        let regions = layout(size, self.notification.is_some(), self.explorer.width());
+        self.explorer.set_bounds(regions.explorer);
+        self.tab_bar.set_bounds(regions.tabs);
+        self.panes.set_bounds(regions.panes);
+        self.status_bar.set_bounds(regions.status);
+        self.request_redraw();
+    }
+
+    pub fn split(&mut self, axis: Axis) {
+        self.panes.split(axis);
+    }
+
+    pub fn close_pane(&mut self) {
+        self.panes.close_focused();
+    }
+
+    pub fn focus(&mut self, direction: Direction) {
+        self.panes.focus_direction(direction);
+    }
+
+    /// Moves the cursors of every other view of the focused document along with `change`.
+    fn propagate_change(&mut self, change: Change) {
+        let document = self.active_document();
+        for pane in self.panes.unfocused_mut() {
+            if pane.document == document {
+                pane.view.adjust_for_change(&change);
+            }
+        }
+        if let Some(view) = self.stashed_views[document].as_mut() {
+            view.adjust_for_change(&change);
         }
     }
 
     pub fn handle_char_input(&mut self, input: char) {
-        self.buffers[self.active_buffer].handle_char_input(input);
+        if self.swallow_char {
+            self.swallow_char = false;
+            return;
+        }
+
+        self.request_redraw();
+        if let Some((prompt, _)) = self.prompt.as_mut() {
+            if let Some(answer) = prompt.handle_char_input(input) {
+                let (_, purpose) = self.prompt.take().unwrap();
+                self.handle_prompt_answer(purpose, answer);
+            }
+            return;
+        }
+
+        if let Some((palette, _)) = self.palette.as_mut() {
+            if let Some(answer) = palette.handle_char_input(input) {
+                let (_, purpose) = self.palette.take().unwrap();
+                self.handle_palette_answer(purpose, answer);
+            }
+            return;
+        }
+
+        if self.explorer_focused || self.refuse_readonly_edit() {
+            return;
+        }
+
+        let pane = self.panes.focused_mut();
+        let change = pane
+            .view
+            .handle_char_input(&mut self.documents[pane.document], input);
+        if let Some(change) = change {
+            self.propagate_change(change);
+        }
+    }
+
+    /// Tells the user the focused document can't be edited if it is read-only. Returns whether
+    /// it is.
+    fn refuse_readonly_edit(&mut self) -> bool {
+        let document = &self.documents[self.panes.focused().document];
+        if !document.is_readonly() {
+            return false;
+        }
+        let message = format!("{} is read-only", document.title());
+        self.set_status_message(message);
+        true
+    }
+
+    pub fn undo(&mut self) {
+        if self.prompt.is_some() || self.refuse_readonly_edit() {
+            return;
+        }
+        let pane = self.panes.focused_mut();
+        if let Some(change) = pane.view.undo(&mut self.documents[pane.document]) {
+            self.propagate_change(change);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.prompt.is_some() || self.refuse_readonly_edit() {
+            return;
+        }
+        let pane = self.panes.focused_mut();
+        if let Some(change) = pane.view.redo(&mut self.documents[pane.document]) {
+            self.propagate_change(change);
+        }
+    }
+
+    pub fn handle_keyboard_input(&mut self, input: KeyboardInput, modifiers: ModifiersState) {
+        if input.state == ElementState::Pressed {
+            self.swallow_char = false;
+            self.request_redraw();
+        }
+        if let Some((prompt, _)) = self.prompt.as_mut() {
+            if let Some(answer) = prompt.handle_keyboard_input(input) {
+                let (_, purpose) = self.prompt.take().unwrap();
+                self.handle_prompt_answer(purpose, answer);
+            }
+            return;
+        }
+
+        if let Some((palette, _)) = self.palette.as_mut() {
+            if let Some(answer) = palette.handle_keyboard_input(input) {
+                let (_, purpose) = self.palette.take().unwrap();
+                self.handle_palette_answer(purpose, answer);
+            }
+            return;
+        }
+
+        if input.state == ElementState::Pressed
+            && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+            && self.notification.is_some()
+        {
+            self.keymap.reset();
+            self.dismiss_notification();
+            return;
+        }
+
+        if input.state == ElementState::Pressed {
+            if let Some(press) = input
+                .virtual_keycode
+                .and_then(|keycode| keymap::from_winit(keycode, modifiers))
+            {
+                if self.explorer_focused {
+                    if let Some(action) = self.explorer.handle_key_press(press) {
+                        self.swallow_char = true;
+                        self.handle_explorer_action(action);
+                        return;
+                    }
+                }
+                self.handle_key_press(press);
+            }
+        }
+    }
+
+    /// Runs a key press through the keymap. Returns whether it was used, in which case the
+    /// character it types is ignored.
+    pub fn handle_key_press(&mut self, press: KeyPress) -> bool {
+        let used = match self.keymap.press(press) {
+            KeyResult::Command(command) => {
+                self.execute(command);
+                true
+            }
+            KeyResult::Pending => true,
+            // Escape is how a chord is abandoned on purpose.
+            KeyResult::Cancelled(ref chord) if chord.last().unwrap().key == Key::Escape => true,
+            KeyResult::Cancelled(chord) => {
+                self.set_status_message(format!(
+                    "{} is not bound to a command",
+                    keymap::chord_to_string(&chord)
+                ));
+                true
+            }
+            KeyResult::Unbound => false,
+        };
+        self.swallow_char = used;
+        used
+    }
+
+    pub fn execute(&mut self, command: Command) {
+        if self.prompt.is_some() {
+            return;
+        }
+        match command {
+            Command::ShowCommands => self.open_command_palette(),
+            Command::FindFile => self.open_file_finder(),
+            Command::ToggleExplorer => {
+                let visible = !self.explorer.is_visible();
+                self.explorer.set_visible(visible);
+                self.explorer_focused = visible;
+                self.update_size(self.size);
+            }
+            Command::FocusExplorer => {
+                if !self.explorer.is_visible() {
+                    self.explorer.set_visible(true);
+                    self.update_size(self.size);
+                }
+                self.explorer_focused = !self.explorer_focused;
+            }
+            Command::NewFile => {
+                let dir = self.explorer.selected_dir();
+                self.prompt_in_dir("New file:", PromptPurpose::CreateFile(dir));
+            }
+            Command::NewFolder => {
+                let dir = self.explorer.selected_dir();
+                self.prompt_in_dir("New folder:", PromptPurpose::CreateFolder(dir));
+            }
+            Command::RenameEntry => {
+                if let Some(path) = self.explorer.selected_path() {
+                    let action = ExplorerAction::Rename(path.to_owned());
+                    self.handle_explorer_action(action);
+                }
+            }
+            Command::DeleteEntry => {
+                if let Some(path) = self.explorer.selected_path() {
+                    let action = ExplorerAction::Delete(path.to_owned());
+                    self.handle_explorer_action(action);
+                }
+            }
+            Command::Quit => self.request_quit(),
+            Command::Save => self.save(),
+            Command::SaveAs => self.save_as(),
+            Command::Rename => self.rename(),
+            Command::UseLf => self.set_line_ending(LineEnding::Lf),
+            Command::UseCrLf => self.set_line_ending(LineEnding::CrLf),
+            Command::ToggleAutosave => self.toggle_autosave_on_focus_loss(),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::SplitHorizontal => self.split(Axis::Horizontal),
+            Command::SplitVertical => self.split(Axis::Vertical),
+            Command::ClosePane => self.close_pane(),
+            Command::FocusLeft => self.focus(Direction::Left),
+            Command::FocusRight => self.focus(Direction::Right),
+            Command::FocusUp => self.focus(Direction::Up),
+            Command::FocusDown => self.focus(Direction::Down),
+            Command::CursorLeft => self.move_cursor(Direction::Left),
+            Command::CursorRight => self.move_cursor(Direction::Right),
+            Command::CursorUp => self.move_cursor(Direction::Up),
+            Command::CursorDown => self.move_cursor(Direction::Down),
+            Command::GoToLine => {
+                let count = self.documents[self.panes.focused().document].line_count();
+                let title = format!("Go to line 1-{}, line:column or +/- lines:", count);
+                let prompt = Prompt::text_input(title, String::new());
+                self.prompt = Some((prompt, PromptPurpose::GoToLine));
+            }
+            Command::GoToSymbol => self.open_symbol_list(),
+        }
     }
 
-    pub fn handle_keyboard_input(&mut self, input: KeyboardInput) {
-        self.buffers[self.active_buffer].handle_keyboard_input(input);
+    /// Lists every command along with the keys bound to it.
+    fn open_command_palette(&mut self) {
+        let commands = Command::ALL.to_vec();
+        let items = commands
+            .iter()
+            .map(|&command| PaletteItem {
+                label: command.title().to_owned(),
+                detail: self
+                    .keymap
+                    .chords_for(command)
+                    .map(|chord| keymap::chord_to_string(chord))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+            .collect();
+        let palette = Palette::new("Type a command".to_owned(), items);
+        self.palette = Some((palette, PalettePurpose::Commands(commands)));
+    }
+
+    /// Lists the symbols in the focused document.
+    fn open_symbol_list(&mut self) {
+        let document = &self.documents[self.panes.focused().document];
+        let symbols = document.symbols();
+        if symbols.is_empty() {
+            let message = format!("No symbols found in {}", document.title());
+            self.set_status_message(message);
+            return;
+        }
+        let items = symbols
+            .iter()
+            .map(|symbol| PaletteItem {
+                label: symbol.name.clone(),
+                detail: format!("{}, line {}", symbol.kind, symbol.location.row + 1),
+            })
+            .collect();
+        let locations = symbols.iter().map(|symbol| symbol.location).collect();
+        let palette = Palette::new("Go to symbol".to_owned(), items);
+        self.palette = Some((palette, PalettePurpose::Symbols(locations)));
+    }
+
+    /// Moves the cursor in the focused pane to the line, and column, `input` points to.
+    fn go_to_line(&mut self, input: &str) {
+        let pane = self.panes.focused_mut();
+        let document = &self.documents[pane.document];
+        let (row, col) = match parse_line_target(input, pane.view.cursor_location().row) {
+            Some(target) => target,
+            None => {
+                let message = format!("{} is not a line number", input.trim());
+                self.set_status_message(message);
+                return;
+            }
+        };
+        let location = document.location_at(row, col);
+        pane.view.go_to(document, location);
+    }
+
+    /// Lists the files in the project, as they are found in the background.
+    fn open_file_finder(&mut self) {
+        let root = match std::env::current_dir() {
+            Ok(root) => root,
+            Err(error) => {
+                self.report_error(format!("Failed to list files: {}", error));
+                return;
+            }
+        };
+        let proxy = self.event_proxy.clone();
+        let index = FileIndex::start(root, move || {
+            if let Some(proxy) = &proxy {
+                // Fails only when the event loop is gone, and then there is nobody to tell.
+                let _ = proxy.send_event(UserEvent::FilesIndexed);
+            }
+        });
+        let palette = Palette::new("Go to file (indexing...)".to_owned(), vec![]);
+        self.palette = Some((palette, PalettePurpose::Files(index)));
+    }
+
+    /// Adds the files the index found since last time to the file finder.
+    fn poll_file_index(&mut self) -> bool {
+        let (palette, index) = match self.palette.as_mut() {
+            Some((palette, PalettePurpose::Files(index))) => (palette, index),
+            _ => return false,
+        };
+        let start = index.files().len();
+        if !index.poll() {
+            return false;
+        }
+        palette.extend_items(index.files()[start..].iter().map(|path| PaletteItem {
+            label: path.to_string_lossy().into_owned(),
+            detail: String::new(),
+        }));
+        if index.is_done() {
+            palette.set_placeholder("Go to file".to_owned());
+        }
+        true
+    }
+
+    fn handle_palette_answer(&mut self, purpose: PalettePurpose, answer: PaletteAnswer) {
+        let index = match answer {
+            PaletteAnswer::Pick(index) => index,
+            PaletteAnswer::Cancel => return,
+        };
+        match purpose {
+            PalettePurpose::Commands(commands) => self.execute(commands[index]),
+            PalettePurpose::Files(files) => {
+                let path = files.root().join(&files.files()[index]);
+                self.open_file(&path);
+            }
+            PalettePurpose::Symbols(locations) => {
+                let pane = self.panes.focused_mut();
+                pane.view
+                    .go_to(&self.documents[pane.document], locations[index]);
+            }
+        }
+    }
+
+    /// Shows the file at `path` in the focused pane, opening it unless it already is.
+    /// Returns the index of the document, or `None` if it failed to open.
+    fn open_file(&mut self, path: &Path) -> Option<usize> {
+        let canonical = fs::canonicalize(path).ok();
+        let open = self.documents.iter().position(|document| {
+            document.path().is_some_and(|open| {
+                open == path || (canonical.is_some() && fs::canonicalize(open).ok() == canonical)
+            })
+        });
+        let index = match open {
+            Some(index) => index,
+            None => match Document::open(path) {
+                Ok(mut document) => {
+                    document.set_theme(&self.config.theme);
+                    self.documents.push(document);
+                    self.stashed_views.push(None);
+                    self.warn_about_line_endings(self.documents.len() - 1);
+                    self.documents.len() - 1
+                }
+                Err(error) => {
+                    self.report_error(format!("Failed to open {}: {}", path.display(), error));
+                    return None;
+                }
+            },
+        };
+        self.show_document(index);
+        Some(index)
+    }
+
+    fn handle_explorer_action(&mut self, action: ExplorerAction) {
+        match action {
+            ExplorerAction::Changed => {}
+            ExplorerAction::Open(path) => {
+                self.open_file(&path);
+                self.explorer_focused = false;
+            }
+            ExplorerAction::Rename(path) => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let prompt = Prompt::text_input("Rename to:".to_owned(), name);
+                self.prompt = Some((prompt, PromptPurpose::RenameEntry(path)));
+            }
+            ExplorerAction::Delete(path) => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let lines = if path.is_dir() {
+                    vec!["The folder and everything in it will be deleted.".to_owned()]
+                } else {
+                    vec![]
+                };
+                let prompt = Prompt::choose(
+                    format!("Delete {}?", name),
+                    lines,
+                    vec![
+                        Choice {
+                            label: "Delete",
+                            shortcut: 'd',
+                        },
+                        Choice {
+                            label: "Cancel",
+                            shortcut: 'c',
+                        },
+                    ],
+                );
+                self.prompt = Some((prompt, PromptPurpose::DeleteEntry(path)));
+            }
+            ExplorerAction::Leave => self.explorer_focused = false,
+        }
+    }
+
+    /// Asks for the name of a new file or folder in the directory of `purpose`.
+    fn prompt_in_dir(&mut self, title: &str, purpose: PromptPurpose) {
+        let prompt = Prompt::text_input(title.to_owned(), String::new());
+        self.prompt = Some((prompt, purpose));
+    }
+
+    /// Creates the file or folder `name` in `dir`, along with any missing folders on the way.
+    fn create_entry(&mut self, dir: &Path, name: &str, is_dir: bool) {
+        let path = dir.join(name);
+        let result = if path.exists() {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "it already exists",
+            ))
+        } else if is_dir {
+            fs::create_dir_all(&path)
+        } else {
+            path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| {
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)
+                        .map(drop)
+                })
+        };
+        if let Err(error) = result {
+            self.report_error(format!("Failed to create {}: {}", path.display(), error));
+            return;
+        }
+
+        self.explorer.reveal(&path);
+        if !is_dir {
+            self.open_file(&path);
+        }
+    }
+
+    /// Renames a file or folder, pointing documents open from inside it at the new location.
+    fn rename_entry(&mut self, path: &Path, name: &str) {
+        let new_path = path.with_file_name(name);
+        if new_path.exists() {
+            self.report_error(format!("Failed to rename: {} exists", new_path.display()));
+            return;
+        }
+        // Documents may have been opened through another path to the same file, so compare the
+        // real paths, which can't be worked out for the old location after the move.
+        let old_real = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let moved: Vec<(usize, PathBuf)> = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter_map(|(index, document)| {
+                let real = fs::canonicalize(document.path()?).ok()?;
+                let rest = real.strip_prefix(&old_real).ok()?;
+                Some((index, rest.to_owned()))
+            })
+            .collect();
+
+        if let Err(error) = fs::rename(path, &new_path) {
+            self.report_error(format!("Failed to rename {}: {}", path.display(), error));
+            return;
+        }
+        for (index, rest) in moved {
+            let document_path = if rest.as_os_str().is_empty() {
+                new_path.clone()
+            } else {
+                new_path.join(rest)
+            };
+            self.documents[index].follow_move(document_path);
+        }
+        self.explorer.reveal(&new_path);
+    }
+
+    fn delete_entry(&mut self, path: &Path) {
+        let result = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        if let Err(error) = result {
+            self.report_error(format!("Failed to delete {}: {}", path.display(), error));
+        }
+        self.explorer.refresh();
+    }
+
+    /// Picks up files created or deleted by other programs in the explorer.
+    pub fn check_explorer_changes(&mut self) {
+        if self.explorer.is_visible() && self.explorer.refresh() {
+            self.request_redraw();
+        }
+    }
+
+    fn move_cursor(&mut self, direction: Direction) {
+        let pane = self.panes.focused_mut();
+        pane.view
+            .move_cursor(&self.documents[pane.document], direction);
     }
 
     pub fn handle_mouse_input(
@@ -46,23 +1841,528 @@ impl Editor {
         state: ElementState,
         position: PhysicalPosition<i32>,
     ) {
-        self.buffers[self.active_buffer].handle_mouse_input(button, state, position);
+        self.request_redraw();
+        if state == ElementState::Released {
+            self.panes.end_divider_drag();
+        }
+
+        // Prompts are modal, the rest of the editor doesn't get clicks while one is open.
+        if self.prompt.is_some() {
+            return;
+        }
+        // Clicking anywhere closes the palette, like Escape.
+        if self.palette.is_some() {
+            if state == ElementState::Pressed {
+                self.palette = None;
+            }
+            return;
+        }
+
+        let regions = self.regions();
+        if regions.notification.contains(position) {
+            if state == ElementState::Pressed {
+                self.dismiss_notification();
+            }
+            return;
+        }
+
+        if self.explorer.contains(position) || self.explorer.is_resizing() {
+            if state == ElementState::Pressed {
+                self.explorer_focused = true;
+            }
+            if let Some(action) = self.explorer.handle_mouse_input(button, state, position) {
+                self.handle_explorer_action(action);
+            }
+            return;
+        }
+
+        if self.tab_bar.contains(position) || self.tab_bar.is_dragging() {
+            if let Some(action) = self.tab_bar.handle_mouse_input(button, state, position) {
+                self.handle_tab_action(action);
+            }
+            // Make sure a drag that started in a pane doesn't get stuck when released over the tabs.
+            if state == ElementState::Released {
+                let pane = self.panes.focused_mut();
+                pane.view.handle_mouse_input(
+                    &self.documents[pane.document],
+                    button,
+                    state,
+                    position,
+                );
+            }
+            return;
+        }
+
+        if state == ElementState::Pressed && button == MouseButton::Left {
+            if self.panes.begin_divider_drag(position) {
+                return;
+            }
+            self.panes.focus_at(position);
+            self.explorer_focused = false;
+        }
+
+        let pane = self.panes.focused_mut();
+        pane.view
+            .handle_mouse_input(&self.documents[pane.document], button, state, position);
     }
 
     pub fn handle_mouse_move(&mut self, position: PhysicalPosition<i32>) {
-        self.buffers[self.active_buffer].handle_mouse_move(position);
+        self.mouse_position = position;
+
+        if self.tab_bar.is_dragging() {
+            if let Some(action) = self.tab_bar.handle_mouse_move(position) {
+                self.handle_tab_action(action);
+                self.request_redraw();
+            }
+            return;
+        }
+
+        if self.panes.is_dragging_divider() {
+            self.panes.drag_divider(position);
+            self.request_redraw();
+            return;
+        }
+
+        if self.explorer.is_resizing() {
+            // Leave some room for the panes.
+            let max_width = self.size.width as f32 - 200.0;
+            self.explorer.resize(position, max_width);
+            self.update_size(self.size);
+            return;
+        }
+
+        let pane = self.panes.focused_mut();
+        if pane
+            .view
+            .handle_mouse_move(&self.documents[pane.document], position)
+        {
+            self.request_redraw();
+        }
     }
 
-    pub fn draw(
-        &self,
-        size: PhysicalSize<u32>,
-        glyph_brush: &mut GlyphBrush<()>,
-        rect_brush: &mut RectangleBrush,
-    ) {
-        self.buffers[self.active_buffer].draw(size, glyph_brush, rect_brush);
+    fn handle_tab_action(&mut self, action: TabAction) {
+        match action {
+            TabAction::Activate(index) => self.show_document(index),
+            TabAction::Close(index) => self.close_document(index),
+            TabAction::Move { from, to } => {
+                let document = self.documents.remove(from);
+                self.documents.insert(to, document);
+                let view = self.stashed_views.remove(from);
+                self.stashed_views.insert(to, view);
+                for pane in self.panes.iter_mut() {
+                    pane.document = moved_index(pane.document, from, to);
+                }
+            }
+        }
+    }
+
+    /// Shows the document at `index` in the focused pane.
+    fn show_document(&mut self, index: usize) {
+        let pane = self.panes.focused_mut();
+        if pane.document == index {
+            return;
+        }
+
+        let mut view = self.stashed_views[index]
+            .take()
+            .unwrap_or_else(|| View::new(pane.view.bounds()));
+        view.set_bounds(pane.view.bounds());
+        view.clamp_to(&self.documents[index]);
+
+        let previous = std::mem::replace(&mut pane.view, view);
+        self.stashed_views[pane.document] = Some(previous);
+        pane.document = index;
+        self.configure_views();
+    }
+
+    fn close_document(&mut self, index: usize) {
+        // There is nothing to show without a document, so the last one stays open.
+        if self.documents.len() == 1 {
+            return;
+        }
+
+        if self.documents[index].is_modified() {
+            let prompt = Prompt::choose(
+                format!("Save changes to {}?", self.documents[index].title()),
+                vec![],
+                vec![
+                    Choice {
+                        label: "Save",
+                        shortcut: 's',
+                    },
+                    Choice {
+                        label: "Discard",
+                        shortcut: 'd',
+                    },
+                    Choice {
+                        label: "Cancel",
+                        shortcut: 'c',
+                    },
+                ],
+            );
+            self.prompt = Some((prompt, PromptPurpose::CloseDocument(index)));
+            return;
+        }
+
+        self.remove_document(index);
+    }
+
+    fn remove_document(&mut self, index: usize) {
+        self.documents.remove(index);
+        self.stashed_views.remove(index);
+
+        // Panes that showed the closed document fall back to its neighbour.
+        let fallback = index.min(self.documents.len() - 1);
+        for pane in self.panes.iter_mut() {
+            if pane.document == index {
+                pane.document = fallback;
+                pane.view = View::new(pane.view.bounds());
+            } else if pane.document > index {
+                pane.document -= 1;
+            }
+        }
+        self.configure_views();
+        #[cfg(unix)]
+        self.release_waiting_clients();
+    }
+
+    pub fn draw(&mut self, renderer: &mut Renderer) {
+        let focused: Vec<bool> = self
+            .panes
+            .iter()
+            .map(|pane| self.panes.is_focused(pane) && !self.explorer_focused)
+            .collect();
+        for (pane, focused) in self.panes.iter_mut().zip(focused) {
+            pane.view
+                .draw(&self.documents[pane.document], focused, renderer);
+        }
+        self.panes.draw_dividers(renderer);
+        if self.explorer.is_visible() {
+            self.explorer.draw(self.explorer_focused, renderer);
+        }
+
+        let tabs: Vec<Tab> = self
+            .documents
+            .iter()
+            .map(|document| Tab {
+                title: document.title(),
+                modified: document.is_modified(),
+            })
+            .collect();
+        let active = self.active_document();
+        self.tab_bar.draw(&tabs, active, renderer);
+
+        if let Some(notification) = &self.notification {
+            let regions = layout(self.size, true, self.explorer.width());
+            notification.draw(regions.notification, renderer);
+        }
+
+        let (left, right) = self.status_segments();
+        self.status_bar.draw(&left, &right, renderer);
+
+        // Prompts and the palette float over the rest of the window.
+        let window = Rect::new(0.0, 0.0, self.size.width as f32, self.size.height as f32);
+        if let Some((prompt, _)) = &self.prompt {
+            prompt.draw(window, renderer);
+        } else if let Some((palette, _)) = &self.palette {
+            palette.draw(window, renderer);
+        }
+    }
+
+    /// What the status bar shows on the left and right for the focused document.
+    fn status_segments(&self) -> (Vec<String>, Vec<String>) {
+        let pane = self.panes.focused();
+        let document = &self.documents[pane.document];
+
+        let location = pane.view.cursor_location();
+        let col = document
+            .line(location.row)
+            .get(..location.col)
+            .map_or(location.col, |before| before.chars().count());
+        let mut left = vec![format!("Ln {}, Col {}", location.row + 1, col + 1)];
+        let selected = pane.view.selection_len(document);
+        if selected > 0 {
+            left.push(format!("{} selected", selected));
+        }
+        if !self.keymap.pending().is_empty() {
+            left.push(format!(
+                "{} was pressed, waiting for the next key",
+                keymap::chord_to_string(self.keymap.pending())
+            ));
+        }
+
+        let mut right = vec![];
+        if self.autosave_on_focus_loss {
+            right.push("Auto-save".to_owned());
+        }
+        if document.is_readonly() {
+            right.push("Read-only".to_owned());
+        }
+        if document.is_modified() {
+            right.push("Unsaved".to_owned());
+        }
+        let language = self.config.language(document.syntax_name());
+        right.push(if language.insert_spaces {
+            format!("Spaces: {}", language.tab_size)
+        } else {
+            format!("Tab Size: {}", language.tab_size)
+        });
+        let format = document.format();
+        right.push(if format.bom {
+            format!("{} with BOM", format.encoding)
+        } else {
+            format.encoding.to_string()
+        });
+        right.push(format.line_ending.to_string());
+        right.push(document.syntax_name().to_owned());
+        (left, right)
+    }
+
+    /// Shows `message` in the status bar for a few seconds.
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_bar.set_message(message);
+        self.request_redraw();
+    }
+
+    /// Clears the status bar message once it has been up long enough.
+    pub fn expire_status_message(&mut self) {
+        if self.status_bar.expire_message() {
+            self.request_redraw();
+        }
     }
 
     pub fn scroll(&mut self, delta: f32) {
-        self.buffers[self.active_buffer].scroll(delta);
+        let position = self.mouse_position;
+        if self.explorer.contains(position) {
+            self.explorer.scroll(delta);
+            self.request_redraw();
+            return;
+        }
+        if let Some(pane) = self.panes.pane_at_mut(position) {
+            pane.view.scroll(&self.documents[pane.document], delta);
+            self.request_redraw();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::FileArg;
+    use std::{
+        env, process,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A directory of its own for a test, removed again afterwards.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let name = format!(
+                "brewcode-test-{}-{}",
+                process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed)
+            );
+            let dir = env::temp_dir().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// An editor with `files` open. Sessions and journals go to a data directory of the tests'
+    /// own rather than the user's.
+    fn editor(files: &[&Path]) -> Editor {
+        crate::dirs::use_test_data_dir();
+        let args = Args {
+            files: files
+                .iter()
+                .map(|path| FileArg {
+                    source: Source::Path(path.to_path_buf()),
+                    position: None,
+                })
+                .collect(),
+            ..Args::default()
+        };
+        Editor::hermetic(PhysicalSize::new(800, 600), &args)
+    }
+
+    /// Adds an untitled document holding `text`, returning its index.
+    fn open_untitled(editor: &mut Editor, text: &str) -> usize {
+        let mut document = Document::untitled();
+        document.insert(Location::new(), text);
+        editor.documents.push(document);
+        editor.stashed_views.push(None);
+        editor.documents.len() - 1
+    }
+
+    /// Gives `answer` to the prompt that is open.
+    fn answer(editor: &mut Editor, answer: PromptAnswer) {
+        let (_, purpose) = editor.prompt.take().expect("no prompt is open");
+        editor.handle_prompt_answer(purpose, answer);
+    }
+
+    fn text(path: &Path) -> PromptAnswer {
+        PromptAnswer::Text(path.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn line_targets() {
+        assert_eq!(parse_line_target("12", 5), Some((11, 0)));
+        assert_eq!(parse_line_target(" 12:5 ", 5), Some((11, 4)));
+        assert_eq!(parse_line_target("+3", 5), Some((8, 0)));
+        assert_eq!(parse_line_target("-3", 5), Some((2, 0)));
+        // Line and column 0 are taken as 1.
+        assert_eq!(parse_line_target("0", 5), Some((0, 0)));
+        assert_eq!(parse_line_target("12:0", 5), Some((11, 0)));
+        // Moving past either end stops there.
+        assert_eq!(parse_line_target("-9", 5), Some((0, 0)));
+        let max = usize::MAX.to_string();
+        assert_eq!(
+            parse_line_target(&format!("+{}", max), 5),
+            Some((usize::MAX, 0))
+        );
+        assert_eq!(parse_line_target(&format!("-{}", max), 5), Some((0, 0)));
+    }
+
+    #[test]
+    fn bad_line_targets() {
+        for input in &[
+            "", "+", "-", "a", "12a", "1:", ":5", "1:2:3", "+-3", "--3", "1.5",
+        ] {
+            assert_eq!(parse_line_target(input, 5), None, "{:?}", input);
+        }
+        // Too big to be any line.
+        assert_eq!(parse_line_target("99999999999999999999999", 5), None);
+        assert_eq!(parse_line_target("+99999999999999999999999", 5), None);
+    }
+
+    #[test]
+    fn going_past_the_last_line_stops_there() {
+        let dir = TestDir::new();
+        let path = dir.path("a.txt");
+        fs::write(&path, "one\ntwo\nthree").unwrap();
+        let mut editor = editor(&[&path]);
+        editor.go_to_line("1000:3");
+        assert_eq!(
+            editor.panes.focused().view.cursor_location(),
+            editor.documents[0].location_at(2, 2)
+        );
+    }
+
+    #[test]
+    fn closing_an_untitled_document_saves_it_first() {
+        let dir = TestDir::new();
+        let mut editor = editor(&[&dir.path("a.txt")]);
+        let index = open_untitled(&mut editor, "hello");
+
+        editor.close_document(index);
+        answer(&mut editor, PromptAnswer::Choice(0));
+        assert_eq!(editor.documents.len(), 2);
+        answer(&mut editor, text(&dir.path("b.txt")));
+
+        assert_eq!(fs::read_to_string(dir.path("b.txt")).unwrap(), "hello");
+        assert_eq!(editor.documents.len(), 1);
+        assert!(editor.prompt.is_none());
+    }
+
+    #[test]
+    fn renaming_onto_an_existing_file_asks_first() {
+        let dir = TestDir::new();
+        fs::write(dir.path("a.txt"), "a").unwrap();
+        fs::write(dir.path("b.txt"), "b").unwrap();
+        let mut editor = editor(&[&dir.path("a.txt")]);
+
+        editor.prompt_for_path("Rename to:", 0, PromptPurpose::Rename(0));
+        answer(&mut editor, text(&dir.path("b.txt")));
+        assert_eq!(fs::read_to_string(dir.path("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.path("b.txt")).unwrap(), "b");
+        answer(&mut editor, PromptAnswer::Choice(1));
+        assert_eq!(fs::read_to_string(dir.path("b.txt")).unwrap(), "b");
+        assert_eq!(
+            editor.documents[0].path(),
+            Some(dir.path("a.txt").as_path())
+        );
+
+        editor.prompt_for_path("Rename to:", 0, PromptPurpose::Rename(0));
+        answer(&mut editor, text(&dir.path("b.txt")));
+        answer(&mut editor, PromptAnswer::Choice(0));
+        assert!(!dir.path("a.txt").exists());
+        assert_eq!(fs::read_to_string(dir.path("b.txt")).unwrap(), "a");
+        assert_eq!(
+            editor.documents[0].path(),
+            Some(dir.path("b.txt").as_path())
+        );
+    }
+
+    #[test]
+    fn journals_put_off_until_later_are_kept() {
+        let dir = TestDir::new();
+        let path = dir.path("a.txt");
+        fs::write(&path, "saved").unwrap();
+        let mut editor = editor(&[&path]);
+        let journal = Journal::File(path.clone());
+        let journaled = vec!["from last time".to_owned()];
+        recovery::write(&journal, &journaled).unwrap();
+        editor.recoveries.push((0, journaled.clone()));
+        editor.pending_journals.insert(journal.clone());
+
+        editor.offer_next_recovery();
+        answer(&mut editor, PromptAnswer::Choice(2));
+        editor.documents[0].insert(Location::new(), "edited ");
+        editor.journal_unsaved();
+        assert_eq!(recovery::read(&path), Some(journaled));
+
+        editor.recoveries.push((0, vec![]));
+        editor.offer_next_recovery();
+        answer(&mut editor, PromptAnswer::Choice(1));
+        editor.journal_unsaved();
+        assert_eq!(recovery::read(&path), Some(vec!["edited saved".to_owned()]));
+        recovery::remove(&journal);
+    }
+
+    // The only test that quits, as the session file is shared by everything started in the
+    // same directory.
+    #[test]
+    fn quitting_with_only_untitled_documents_forgets_the_session() {
+        let dir = TestDir::new();
+        let path = dir.path("a.txt");
+        fs::write(&path, "a").unwrap();
+        let mut with_file = editor(&[&path]);
+        with_file.quit();
+        assert!(with_file.should_quit());
+        assert_eq!(session::load().unwrap().documents[0].path, path);
+
+        let mut untitled = editor(&[]);
+        open_untitled(&mut untitled, "unsaved");
+        untitled.request_quit();
+        answer(&mut untitled, PromptAnswer::Choice(1));
+        assert!(untitled.should_quit());
+        assert!(session::load().is_none());
+    }
+
+    #[test]
+    fn cancelling_save_as_keeps_the_document_open() {
+        let dir = TestDir::new();
+        let mut editor = editor(&[&dir.path("a.txt")]);
+        let index = open_untitled(&mut editor, "hello");
+
+        editor.close_document(index);
+        answer(&mut editor, PromptAnswer::Choice(0));
+        answer(&mut editor, PromptAnswer::Cancel);
+
+        assert_eq!(editor.documents.len(), 2);
+        assert!(editor.closing.is_none());
     }
 }
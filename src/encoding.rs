@@ -0,0 +1,255 @@
+use std::fmt;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::CrLf => write!(f, "CRLF"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    // Anything that isn't valid UTF-8. Every byte maps to one char, so it always round-trips.
+    Latin1,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Utf16Le => write!(f, "UTF-16 LE"),
+            Encoding::Utf16Be => write!(f, "UTF-16 BE"),
+            Encoding::Latin1 => write!(f, "ISO-8859-1"),
+        }
+    }
+}
+
+/// How a file's text is stored on disk, so it can be written back the way it was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    /// Whether some lines of a CRLF file end in a bare LF, which saving turns into CRLF.
+    pub mixed_line_endings: bool,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        Self {
+            encoding: Encoding::Utf8,
+            bom: false,
+            line_ending: LineEnding::Lf,
+            mixed_line_endings: false,
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks(2).map(|pair| from_bytes([pair[0], pair[1]]));
+    std::char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
+
+/// Picks the line ending used by most lines, and whether any line break differs from it. Files
+/// without any line breaks get LF. A '\r' before an LF in an LF file is kept as text, so only
+/// CRLF files count as mixed.
+fn detect_line_ending(text: &str) -> (LineEnding, bool) {
+    let newlines = text.matches('\n').count();
+    let crlfs = text.matches("\r\n").count();
+    if crlfs > 0 && crlfs * 2 >= newlines {
+        (LineEnding::CrLf, crlfs < newlines)
+    } else {
+        (LineEnding::Lf, false)
+    }
+}
+
+/// Decodes the contents of a file, detecting its encoding, BOM and line endings.
+/// The returned text still contains the original line breaks.
+pub fn decode(bytes: &[u8]) -> (String, FileFormat) {
+    let (text, encoding, bom) = if bytes.starts_with(UTF8_BOM) {
+        match std::str::from_utf8(&bytes[UTF8_BOM.len()..]) {
+            Ok(text) => (text.to_owned(), Encoding::Utf8, true),
+            Err(_) => (latin1(bytes), Encoding::Latin1, false),
+        }
+    } else if let Some(text) = bytes
+        .strip_prefix(UTF16_LE_BOM)
+        .and_then(|rest| decode_utf16(rest, u16::from_le_bytes))
+    {
+        (text, Encoding::Utf16Le, true)
+    } else if let Some(text) = bytes
+        .strip_prefix(UTF16_BE_BOM)
+        .and_then(|rest| decode_utf16(rest, u16::from_be_bytes))
+    {
+        (text, Encoding::Utf16Be, true)
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_owned(), Encoding::Utf8, false),
+            Err(_) => (latin1(bytes), Encoding::Latin1, false),
+        }
+    };
+
+    let (line_ending, mixed_line_endings) = detect_line_ending(&text);
+    (
+        text,
+        FileFormat {
+            encoding,
+            bom,
+            line_ending,
+            mixed_line_endings,
+        },
+    )
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+/// Encodes `text` the way `format` describes, including the BOM.
+/// Line breaks are expected to already be in the right style.
+pub fn encode(text: &str, format: FileFormat) -> Vec<u8> {
+    match format.encoding {
+        Encoding::Utf8 => {
+            let mut bytes = if format.bom {
+                UTF8_BOM.to_vec()
+            } else {
+                vec![]
+            };
+            bytes.extend_from_slice(text.as_bytes());
+            bytes
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let little_endian = format.encoding == Encoding::Utf16Le;
+            let mut bytes = match (format.bom, little_endian) {
+                (false, _) => vec![],
+                (true, true) => UTF16_LE_BOM.to_vec(),
+                (true, false) => UTF16_BE_BOM.to_vec(),
+            };
+            for unit in text.encode_utf16() {
+                if little_endian {
+                    bytes.extend_from_slice(&unit.to_le_bytes());
+                } else {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            bytes
+        }
+        // Characters typed in that Latin-1 can't represent are replaced rather than lost silently
+        // as garbage bytes.
+        Encoding::Latin1 => text
+            .chars()
+            .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> FileFormat {
+        let (text, format) = decode(bytes);
+        assert_eq!(encode(&text, format), bytes);
+        format
+    }
+
+    #[test]
+    fn utf8_without_bom() {
+        let format = round_trip("héllo\nwörld\n".as_bytes());
+        assert_eq!(format.encoding, Encoding::Utf8);
+        assert!(!format.bom);
+    }
+
+    #[test]
+    fn utf8_with_bom() {
+        let mut bytes = UTF8_BOM.to_vec();
+        bytes.extend_from_slice("héllo".as_bytes());
+        let format = round_trip(&bytes);
+        assert_eq!(format.encoding, Encoding::Utf8);
+        assert!(format.bom);
+        assert_eq!(decode(&bytes).0, "héllo");
+    }
+
+    #[test]
+    fn utf16_le() {
+        let mut bytes = UTF16_LE_BOM.to_vec();
+        for unit in "a€\n😀".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        let format = round_trip(&bytes);
+        assert_eq!(format.encoding, Encoding::Utf16Le);
+        assert!(format.bom);
+        assert_eq!(decode(&bytes).0, "a€\n😀");
+    }
+
+    #[test]
+    fn utf16_be() {
+        let mut bytes = UTF16_BE_BOM.to_vec();
+        for unit in "a€\n😀".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        let format = round_trip(&bytes);
+        assert_eq!(format.encoding, Encoding::Utf16Be);
+        assert!(format.bom);
+        assert_eq!(decode(&bytes).0, "a€\n😀");
+    }
+
+    #[test]
+    fn invalid_utf8_falls_back_to_latin1() {
+        let bytes = b"caf\xe9 \xff\x80\n";
+        let format = round_trip(bytes);
+        assert_eq!(format.encoding, Encoding::Latin1);
+        assert_eq!(decode(bytes).0, "caf\u{e9} \u{ff}\u{80}\n");
+    }
+
+    #[test]
+    fn latin1_replaces_what_it_cannot_store() {
+        let format = FileFormat {
+            encoding: Encoding::Latin1,
+            ..FileFormat::default()
+        };
+        assert_eq!(encode("é€", format), b"\xe9?");
+    }
+
+    #[test]
+    fn detects_line_endings() {
+        assert_eq!(decode(b"a\r\nb\r\n").1.line_ending, LineEnding::CrLf);
+        assert_eq!(decode(b"a\nb\n").1.line_ending, LineEnding::Lf);
+        assert_eq!(decode(b"no breaks").1.line_ending, LineEnding::Lf);
+        // Mixed files get whichever most lines use, CRLF winning a tie.
+        assert_eq!(decode(b"a\r\nb\r\nc\n").1.line_ending, LineEnding::CrLf);
+        assert_eq!(decode(b"a\r\nb\n").1.line_ending, LineEnding::CrLf);
+        assert_eq!(decode(b"a\r\nb\nc\n").1.line_ending, LineEnding::Lf);
+        assert!(!decode(b"a\r\nb\r\n").1.mixed_line_endings);
+        assert!(decode(b"a\r\nb\r\nc\n").1.mixed_line_endings);
+        assert!(!decode(b"a\r\nb\nc\n").1.mixed_line_endings);
+    }
+}
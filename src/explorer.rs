@@ -0,0 +1,408 @@
+use crate::keymap::{Key, KeyPress};
+use crate::layout::Rect;
+use crate::renderer::{Layer, Renderer};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use wgpu_glyph::{Layout, Scale, Section};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton},
+};
+
+const DEFAULT_WIDTH: f32 = 300.0;
+const MIN_WIDTH: f32 = 120.0;
+const EXPLORER_SCALE: f32 = 24.0;
+const ROW_HEIGHT: f32 = EXPLORER_SCALE + 8.0;
+const EXPLORER_PADDING: f32 = 10.0;
+const INDENT: f32 = 16.0;
+/// The strip along the right edge that can be dragged to resize the explorer.
+const DIVIDER_WIDTH: f32 = 4.0;
+
+struct Entry {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+    depth: usize,
+}
+
+/// Something done in the explorer that the editor needs to act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExplorerAction {
+    /// The selection moved or a directory opened or closed; only a redraw is needed.
+    Changed,
+    Open(PathBuf),
+    Rename(PathBuf),
+    Delete(PathBuf),
+    /// Escape was pressed, handing the keyboard back to the panes.
+    Leave,
+}
+
+/// A tree of the files in the project, shown along the left of the window.
+pub struct Explorer {
+    root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    // The visible rows: the tree flattened, with the contents of expanded directories included.
+    entries: Vec<Entry>,
+    selected: usize,
+    scroll: f32,
+    visible: bool,
+    width: f32,
+    bounds: Rect,
+    resizing: bool,
+}
+
+/// The entries of `dir`, directories first, each group sorted by name.
+fn read_entries(dir: &Path) -> Vec<(PathBuf, String, bool)> {
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name() != ".git")
+            .map(|entry| {
+                // Follows symlinks, so linked directories can be expanded too.
+                let is_dir = entry.path().is_dir();
+                let name = entry.file_name().to_string_lossy().into_owned();
+                (entry.path(), name, is_dir)
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    entries.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+    entries
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            expanded: HashSet::new(),
+            entries: vec![],
+            selected: 0,
+            scroll: 0.0,
+            visible: false,
+            width: DEFAULT_WIDTH,
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+            resizing: false,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if visible {
+            self.refresh();
+        }
+    }
+
+    /// How much of the window the explorer takes up, which is nothing while it is hidden.
+    pub fn width(&self) -> f32 {
+        if self.visible {
+            self.width
+        } else {
+            0.0
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        self.clamp_scroll();
+    }
+
+    pub fn contains(&self, position: PhysicalPosition<i32>) -> bool {
+        self.visible && self.bounds.contains(position)
+    }
+
+    /// Reads the tree from disk again. Returns whether anything changed, which is how the
+    /// explorer keeps up with files created or deleted by other programs.
+    pub fn refresh(&mut self) -> bool {
+        let mut entries = vec![];
+        let mut stack: Vec<(PathBuf, String, bool, usize)> = read_entries(&self.root)
+            .into_iter()
+            .rev()
+            .map(|(path, name, is_dir)| (path, name, is_dir, 0))
+            .collect();
+        while let Some((path, name, is_dir, depth)) = stack.pop() {
+            if is_dir && self.expanded.contains(&path) {
+                stack.extend(
+                    read_entries(&path)
+                        .into_iter()
+                        .rev()
+                        .map(|(path, name, is_dir)| (path, name, is_dir, depth + 1)),
+                );
+            }
+            entries.push(Entry {
+                path,
+                name,
+                is_dir,
+                depth,
+            });
+        }
+
+        let unchanged = entries.len() == self.entries.len()
+            && entries
+                .iter()
+                .zip(&self.entries)
+                .all(|(new, old)| new.path == old.path && new.is_dir == old.is_dir);
+        if unchanged {
+            return false;
+        }
+
+        // Keep the same entry selected if it is still there.
+        let selected = self.selected_path().map(Path::to_owned);
+        self.entries = entries;
+        self.selected = selected
+            .and_then(|selected| self.entries.iter().position(|entry| entry.path == selected))
+            .unwrap_or_else(|| self.selected.min(self.entries.len().saturating_sub(1)));
+        self.clamp_scroll();
+        true
+    }
+
+    pub fn selected_path(&self) -> Option<&Path> {
+        self.entries
+            .get(self.selected)
+            .map(|entry| entry.path.as_path())
+    }
+
+    /// Where new files go: the selected directory, or the directory of the selected file.
+    pub fn selected_dir(&self) -> PathBuf {
+        match self.entries.get(self.selected) {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map_or_else(|| self.root.clone(), Path::to_owned),
+            None => self.root.clone(),
+        }
+    }
+
+    /// Expands the directories leading to `path` and selects it.
+    pub fn reveal(&mut self, path: &Path) {
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if !dir.starts_with(&self.root) || dir == self.root {
+                break;
+            }
+            self.expanded.insert(dir.to_owned());
+            parent = dir.parent();
+        }
+        self.refresh();
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.select(index);
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+        let top = self.selected as f32 * ROW_HEIGHT;
+        let visible_height = self.bounds.height - EXPLORER_PADDING;
+        if top < self.scroll {
+            self.scroll = top;
+        } else if top + ROW_HEIGHT > self.scroll + visible_height {
+            self.scroll = top + ROW_HEIGHT - visible_height;
+        }
+    }
+
+    fn clamp_scroll(&mut self) {
+        let max_scroll = (self.entries.len() as f32 * ROW_HEIGHT + EXPLORER_PADDING
+            - self.bounds.height)
+            .max(0.0);
+        self.scroll = self.scroll.clamp(0.0, max_scroll);
+    }
+
+    pub fn scroll(&mut self, delta: f32) {
+        self.scroll += delta;
+        self.clamp_scroll();
+    }
+
+    fn set_expanded(&mut self, index: usize, expanded: bool) {
+        let path = self.entries[index].path.clone();
+        if expanded {
+            self.expanded.insert(path);
+        } else {
+            self.expanded.remove(&path);
+        }
+        self.refresh();
+    }
+
+    /// Opens files and expands or collapses directories.
+    fn activate(&mut self, index: usize) -> ExplorerAction {
+        let entry = &self.entries[index];
+        if entry.is_dir {
+            let expanded = self.expanded.contains(&entry.path);
+            self.set_expanded(index, !expanded);
+            ExplorerAction::Changed
+        } else {
+            ExplorerAction::Open(entry.path.clone())
+        }
+    }
+
+    /// Handles keys while the explorer has the keyboard. Returns `None` for keys it doesn't use,
+    /// which go on to the keymap.
+    pub fn handle_key_press(&mut self, press: KeyPress) -> Option<ExplorerAction> {
+        if press.ctrl || press.alt {
+            return None;
+        }
+        if self.entries.is_empty() {
+            return match press.key {
+                Key::Escape => Some(ExplorerAction::Leave),
+                _ => None,
+            };
+        }
+
+        let page = ((self.bounds.height / ROW_HEIGHT) as usize).max(1);
+        let entry = &self.entries[self.selected];
+        match press.key {
+            Key::Up => self.select(self.selected.saturating_sub(1)),
+            Key::Down => self.select(self.selected + 1),
+            Key::PageUp => self.select(self.selected.saturating_sub(page)),
+            Key::PageDown => self.select(self.selected + page),
+            Key::Home => self.select(0),
+            Key::End => self.select(self.entries.len() - 1),
+            // Right opens a directory, or moves into it once it is open.
+            Key::Right if entry.is_dir => {
+                if self.expanded.contains(&entry.path) {
+                    self.select(self.selected + 1);
+                } else {
+                    self.set_expanded(self.selected, true);
+                }
+            }
+            // Left closes a directory, or moves out to the parent.
+            Key::Left => {
+                if entry.is_dir && self.expanded.contains(&entry.path) {
+                    self.set_expanded(self.selected, false);
+                } else if let Some(parent) = self.entries[..self.selected]
+                    .iter()
+                    .rposition(|other| other.depth < entry.depth)
+                {
+                    self.select(parent);
+                }
+            }
+            Key::Enter | Key::Space => return Some(self.activate(self.selected)),
+            Key::F(2) => return Some(ExplorerAction::Rename(entry.path.clone())),
+            Key::Delete => return Some(ExplorerAction::Delete(entry.path.clone())),
+            Key::Escape => return Some(ExplorerAction::Leave),
+            _ => return None,
+        }
+        Some(ExplorerAction::Changed)
+    }
+
+    /// Whether the divider along the right edge is being dragged.
+    pub fn is_resizing(&self) -> bool {
+        self.resizing
+    }
+
+    /// Resizes the explorer to end at the mouse, leaving at least `max_width` for the rest.
+    pub fn resize(&mut self, position: PhysicalPosition<i32>, max_width: f32) {
+        self.width = (position.x as f32 - self.bounds.x)
+            .min(max_width)
+            .max(MIN_WIDTH);
+    }
+
+    pub fn handle_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+        position: PhysicalPosition<i32>,
+    ) -> Option<ExplorerAction> {
+        if state == ElementState::Released {
+            self.resizing = false;
+            return None;
+        }
+        if button != MouseButton::Left {
+            return None;
+        }
+        if position.x as f32 >= self.bounds.right() - DIVIDER_WIDTH {
+            self.resizing = true;
+            return None;
+        }
+
+        let row =
+            (position.y as f32 - self.bounds.y - EXPLORER_PADDING / 2.0 + self.scroll) / ROW_HEIGHT;
+        if row < 0.0 || row as usize >= self.entries.len() {
+            return Some(ExplorerAction::Changed);
+        }
+        self.select(row as usize);
+        Some(self.activate(self.selected))
+    }
+
+    pub fn draw(&self, focused: bool, renderer: &mut Renderer) {
+        let bounds = self.bounds;
+        renderer.queue_rectangle(
+            Layer::Background,
+            bounds.x as i32,
+            bounds.y as i32,
+            bounds.width as i32,
+            bounds.height as i32,
+            [0.05, 0.05, 0.05, 1.0],
+        );
+        renderer.queue_rectangle(
+            Layer::Background,
+            (bounds.right() - DIVIDER_WIDTH) as i32,
+            bounds.y as i32,
+            DIVIDER_WIDTH as i32,
+            bounds.height as i32,
+            [0.15, 0.15, 0.15, 1.0],
+        );
+
+        // Rows scrolled partly out of view are cut off at the edges.
+        renderer.push_clip(bounds);
+        let text_width = bounds.width - DIVIDER_WIDTH - EXPLORER_PADDING;
+        let mut y = bounds.y + EXPLORER_PADDING / 2.0 - self.scroll;
+        for (index, entry) in self.entries.iter().enumerate() {
+            if y + ROW_HEIGHT <= bounds.y {
+                y += ROW_HEIGHT;
+                continue;
+            }
+            if y >= bounds.bottom() {
+                break;
+            }
+
+            if index == self.selected {
+                let color = if focused {
+                    [0.15, 0.3, 0.55, 1.0]
+                } else {
+                    [0.12, 0.12, 0.12, 1.0]
+                };
+                renderer.queue_rectangle(
+                    Layer::Background,
+                    bounds.x as i32,
+                    y as i32,
+                    (bounds.width - DIVIDER_WIDTH) as i32,
+                    ROW_HEIGHT as i32,
+                    color,
+                );
+            }
+
+            let marker = match (entry.is_dir, self.expanded.contains(&entry.path)) {
+                (true, true) => "- ",
+                (true, false) => "+ ",
+                (false, _) => "  ",
+            };
+            let text = format!("{}{}", marker, entry.name);
+            let indent = EXPLORER_PADDING + entry.depth as f32 * INDENT;
+            renderer.queue_text(
+                Layer::Text,
+                Section {
+                    text: &text,
+                    screen_position: (bounds.x + indent, y + (ROW_HEIGHT - EXPLORER_SCALE) / 2.0),
+                    bounds: ((text_width - indent).max(0.0), ROW_HEIGHT),
+                    scale: Scale::uniform(EXPLORER_SCALE),
+                    color: if entry.is_dir {
+                        [0.75, 0.8, 0.9, 1.0]
+                    } else {
+                        [0.8, 0.8, 0.8, 1.0]
+                    },
+                    layout: Layout::default_single_line(),
+                    ..Section::default()
+                },
+            );
+            y += ROW_HEIGHT;
+        }
+        renderer.pop_clip();
+    }
+}
@@ -0,0 +1,158 @@
+use crate::gitignore::{self, Gitignore};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+};
+
+/// Files found are sent over in batches of this many, so the list fills in while walking.
+const BATCH_SIZE: usize = 512;
+
+/// A list of the files under a directory, filled in by a background thread. Paths are relative
+/// to the directory and skip whatever `.gitignore` files ignore.
+pub struct FileIndex {
+    root: PathBuf,
+    files: Vec<PathBuf>,
+    receiver: Receiver<Vec<PathBuf>>,
+    done: bool,
+}
+
+impl FileIndex {
+    /// Starts walking `root`, calling `notify` from the walking thread whenever a batch of files
+    /// is ready to be picked up with `poll`.
+    pub fn start(root: PathBuf, notify: impl Fn() + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let walk_root = root.clone();
+        thread::spawn(move || {
+            walk(&walk_root, &sender, &notify);
+            // Dropping the sender tells the index the walk is over, so wake it up for that too.
+            drop(sender);
+            notify();
+        });
+        Self {
+            root,
+            files: vec![],
+            receiver,
+            done: false,
+        }
+    }
+
+    /// Takes in the files found since the last call. Returns whether there were any, or whether
+    /// the walk finished.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while !self.done {
+            match self.receiver.try_recv() {
+                Ok(batch) => {
+                    self.files.extend(batch);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+fn walk(root: &Path, sender: &Sender<Vec<PathBuf>>, notify: &dyn Fn()) {
+    let mut ignores = vec![];
+    if let Ok(text) = fs::read_to_string(root.join(".git/info/exclude")) {
+        ignores.push(Gitignore::parse(PathBuf::new(), &text));
+    }
+    let mut batch = vec![];
+    if walk_dir(
+        root,
+        PathBuf::new(),
+        &mut ignores,
+        &mut batch,
+        sender,
+        notify,
+    )
+    .is_ok()
+        && !batch.is_empty()
+        && sender.send(batch).is_ok()
+    {
+        notify();
+    }
+}
+
+/// Walks `dir`, which is `relative` to the root. Fails once the index has been dropped, to stop
+/// walking for nobody.
+fn walk_dir(
+    dir: &Path,
+    relative: PathBuf,
+    ignores: &mut Vec<Gitignore>,
+    batch: &mut Vec<PathBuf>,
+    sender: &Sender<Vec<PathBuf>>,
+    notify: &dyn Fn(),
+) -> Result<(), ()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // Unreadable directories are skipped rather than ending the walk.
+        Err(_) => return Ok(()),
+    };
+    let pushed = match Gitignore::load(dir, relative.clone()) {
+        Some(ignore) => {
+            ignores.push(ignore);
+            true
+        }
+        None => false,
+    };
+
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut result = Ok(());
+    for entry in entries {
+        let name = entry.file_name();
+        if name == ".git" {
+            continue;
+        }
+        // Symlinks aren't followed, which also keeps loops out.
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        let path = relative.join(&name);
+        if gitignore::is_ignored(ignores, &path, file_type.is_dir()) {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            result = walk_dir(&entry.path(), path, ignores, batch, sender, notify);
+            if result.is_err() {
+                break;
+            }
+        } else {
+            batch.push(path);
+            if batch.len() >= BATCH_SIZE {
+                if sender.send(std::mem::take(batch)).is_err() {
+                    result = Err(());
+                    break;
+                }
+                notify();
+            }
+        }
+    }
+
+    if pushed {
+        ignores.pop();
+    }
+    result
+}
@@ -0,0 +1,164 @@
+// Points for a matched character, depending on where it falls in the text.
+const START_BONUS: i64 = 8;
+const WORD_START_BONUS: i64 = 7;
+const CAMEL_CASE_BONUS: i64 = 6;
+const CONSECUTIVE_BONUS: i64 = 5;
+/// Taken off for every character skipped between two matched characters.
+const GAP_PENALTY: i64 = 1;
+
+/// How well a pattern matched some text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    /// Char indices of the matched characters in the text, in order.
+    pub positions: Vec<usize>,
+}
+
+fn bonus(text: &[char], index: usize) -> i64 {
+    if index == 0 {
+        return START_BONUS;
+    }
+    let (previous, current) = (text[index - 1], text[index]);
+    if "/\\_- .".contains(previous) {
+        WORD_START_BONUS
+    } else if previous.is_lowercase() && current.is_uppercase() {
+        CAMEL_CASE_BONUS
+    } else {
+        0
+    }
+}
+
+fn same(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Matches the characters of `pattern` in order anywhere in `text`, ignoring case. Matches at
+/// the start of words and runs of consecutive characters score higher. An empty pattern matches
+/// everything with a score of zero.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: vec![],
+        });
+    }
+
+    // Bail out early, and cheaply, when the pattern isn't a subsequence at all.
+    let mut remaining = pattern.iter().peekable();
+    for &c in &text {
+        if remaining.peek().is_some_and(|&&p| same(p, c)) {
+            remaining.next();
+        }
+    }
+    if remaining.peek().is_some() {
+        return None;
+    }
+
+    // scores[i][j] is the best score for pattern[..=i] with pattern[i] matched at text[j], and
+    // from[i][j] where pattern[i - 1] was matched on the way there.
+    let width = text.len();
+    let mut scores = vec![None; pattern.len() * width];
+    let mut from = vec![0; pattern.len() * width];
+
+    for (j, &c) in text.iter().enumerate() {
+        if same(pattern[0], c) {
+            scores[j] = Some(bonus(&text, j));
+        }
+    }
+    for i in 1..pattern.len() {
+        // The best of scores[i - 1][k] + k * GAP_PENALTY over k < j - 1, so a gap from k to j
+        // costs (j - k - 1) * GAP_PENALTY without looking at every k again.
+        let mut best_before: Option<(i64, usize)> = None;
+        for j in 1..width {
+            if j >= 2 {
+                if let Some(score) = scores[(i - 1) * width + j - 2] {
+                    let candidate = score + (j - 2) as i64 * GAP_PENALTY;
+                    if best_before.is_none_or(|(best, _)| candidate > best) {
+                        best_before = Some((candidate, j - 2));
+                    }
+                }
+            }
+            if !same(pattern[i], text[j]) {
+                continue;
+            }
+
+            let bonus = bonus(&text, j);
+            let adjacent = scores[(i - 1) * width + j - 1]
+                .map(|score| (score + bonus + CONSECUTIVE_BONUS, j - 1));
+            let gapped =
+                best_before.map(|(best, k)| (best - (j - 1) as i64 * GAP_PENALTY + bonus, k));
+            let best = match (adjacent, gapped) {
+                (Some(a), Some(g)) => Some(if a.0 >= g.0 { a } else { g }),
+                (a, g) => a.or(g),
+            };
+            if let Some((score, k)) = best {
+                scores[i * width + j] = Some(score);
+                from[i * width + j] = k;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, score) = (0..width)
+        .filter_map(|j| scores[last * width + j].map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+    let mut positions = vec![j; pattern.len()];
+    for i in (1..pattern.len()).rev() {
+        j = from[i * width + j];
+        positions[i - 1] = j;
+    }
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text).unwrap().score
+    }
+
+    fn positions(pattern: &str, text: &str) -> Vec<usize> {
+        fuzzy_match(pattern, text).unwrap().positions
+    }
+
+    #[test]
+    fn matches_subsequences_ignoring_case() {
+        assert_eq!(positions("MAIN", "src/main.rs"), vec![4, 5, 6, 7]);
+        assert_eq!(positions("s m r", "src/main.rs"), vec![0, 4, 9]);
+        assert_eq!(fuzzy_match("xyz", "src/main.rs"), None);
+        assert_eq!(fuzzy_match("mains", "src/main.r"), None);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        let m = fuzzy_match("", "anything").unwrap();
+        assert_eq!(m.score, 0);
+        assert!(m.positions.is_empty());
+    }
+
+    #[test]
+    fn positions_are_char_indices() {
+        // 'é' is two bytes, but the palette colours characters.
+        assert_eq!(positions("éb", "aébc"), vec![1, 2]);
+        assert_eq!(positions("rs", "ünïcödé/lib.rs"), vec![12, 13]);
+    }
+
+    #[test]
+    fn consecutive_matches_rank_higher() {
+        assert!(score("abc", "xabcx") > score("abc", "xaxbxcx"));
+        assert_eq!(positions("abc", "axbxabc"), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn word_starts_rank_higher() {
+        // The 'b' starting a word beats the nearer one in the middle of a word.
+        assert_eq!(positions("fb", "fab_bar"), vec![0, 4]);
+        assert_eq!(positions("fm", "src/format/main.rs"), vec![4, 11]);
+        assert!(score("fb", "foo_bar") > score("fb", "foobar"));
+        assert!(score("fb", "fooBar") > score("fb", "foobar"));
+        assert!(score("main", "src/main.rs") > score("main", "src/my_animation.rs"));
+    }
+}
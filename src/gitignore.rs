@@ -0,0 +1,264 @@
+//! Matching paths against `.gitignore` files, following the rules in `man gitignore`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+struct Pattern {
+    glob: Vec<char>,
+    /// Patterns starting with `!` re-include what earlier patterns ignored.
+    negated: bool,
+    /// Patterns ending with `/` only match directories.
+    dir_only: bool,
+    /// Patterns with a `/` before the end match the whole path relative to the `.gitignore`,
+    /// others match the file name at any depth.
+    anchored: bool,
+}
+
+/// The patterns of one `.gitignore` file.
+pub struct Gitignore {
+    /// The directory the file is in, relative to the root being walked.
+    base: PathBuf,
+    patterns: Vec<Pattern>,
+}
+
+impl Gitignore {
+    pub fn parse(base: PathBuf, text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let anchored = line.contains('/');
+                let line = line.strip_prefix('/').unwrap_or(line);
+                if line.is_empty() {
+                    return None;
+                }
+                Some(Pattern {
+                    glob: line.chars().collect(),
+                    negated,
+                    dir_only,
+                    anchored,
+                })
+            })
+            .collect();
+        Self { base, patterns }
+    }
+
+    /// Reads the `.gitignore` in `dir`, which is `base` relative to the root, if there is one.
+    pub fn load(dir: &Path, base: PathBuf) -> Option<Self> {
+        let text = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Self::parse(base, &text))
+    }
+
+    /// `Some(true)` if the last pattern matching `path`, relative to the root, ignores it,
+    /// `Some(false)` if it re-includes it and `None` if no pattern matches.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative: Vec<char> = relative
+            .to_string_lossy()
+            .replace('\\', "/")
+            .chars()
+            .collect();
+        let name: Vec<char> = path.file_name()?.to_string_lossy().chars().collect();
+
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| {
+                (is_dir || !pattern.dir_only)
+                    && if pattern.anchored {
+                        glob_match(&pattern.glob, &relative)
+                    } else {
+                        glob_match(&pattern.glob, &name)
+                    }
+            })
+            .map(|pattern| !pattern.negated)
+    }
+}
+
+/// Whether `path` is ignored by `ignores`, which are ordered from the root down. Deeper files
+/// take precedence, as do later patterns within a file.
+pub fn is_ignored(ignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|ignore| ignore.matched(path, is_dir))
+        .unwrap_or(false)
+}
+
+/// Matches a glob with `*`, `?`, `[...]` and `**` against the whole of `text`. Only `**` crosses
+/// `/`.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            // "**/" also matches no directories at all.
+            if rest.first() == Some(&'/') && glob_match(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|skip| glob_match(rest, &text[skip..]))
+        }
+        Some('*') => {
+            let rest = &glob[1..];
+            for skip in 0..=text.len() {
+                if glob_match(rest, &text[skip..]) {
+                    return true;
+                }
+                if text.get(skip) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(&c) if c != '/' => glob_match(&glob[1..], &text[1..]),
+            _ => false,
+        },
+        Some('[') => {
+            let c = match text.first() {
+                Some(&c) if c != '/' => c,
+                _ => return false,
+            };
+            let close = match glob.iter().skip(2).position(|&g| g == ']') {
+                Some(position) => position + 2,
+                // Without a closing bracket it is just a character.
+                None => return c == '[' && glob_match(&glob[1..], &text[1..]),
+            };
+            let class = &glob[1..close];
+            let (negated, class) = match class.first() {
+                Some('!') | Some('^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negated && glob_match(&glob[close + 1..], &text[1..])
+        }
+        Some('\\') if glob.len() > 1 => {
+            text.first() == Some(&glob[1]) && glob_match(&glob[2..], &text[1..])
+        }
+        Some(&g) => text.first() == Some(&g) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(text: &str) -> Gitignore {
+        Gitignore::parse(PathBuf::new(), text)
+    }
+
+    fn file(ignore: &Gitignore, path: &str) -> Option<bool> {
+        ignore.matched(Path::new(path), false)
+    }
+
+    fn dir(ignore: &Gitignore, path: &str) -> Option<bool> {
+        ignore.matched(Path::new(path), true)
+    }
+
+    #[test]
+    fn unanchored_patterns_match_names_at_any_depth() {
+        let ignore = root("*.log\ntarget\n");
+        assert_eq!(file(&ignore, "debug.log"), Some(true));
+        assert_eq!(file(&ignore, "a/b/debug.log"), Some(true));
+        assert_eq!(dir(&ignore, "crates/x/target"), Some(true));
+        assert_eq!(file(&ignore, "debug.log.txt"), None);
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_the_base() {
+        let ignore = root("/build\ndoc/*.txt\n");
+        assert_eq!(dir(&ignore, "build"), Some(true));
+        assert_eq!(dir(&ignore, "src/build"), None);
+        assert_eq!(file(&ignore, "doc/notes.txt"), Some(true));
+        // A single `*` doesn't cross directories.
+        assert_eq!(file(&ignore, "doc/old/notes.txt"), None);
+        assert_eq!(file(&ignore, "src/doc/notes.txt"), None);
+    }
+
+    #[test]
+    fn double_star() {
+        let ignore = root("**/cache\nlogs/**\na/**/b\n");
+        assert_eq!(dir(&ignore, "cache"), Some(true));
+        assert_eq!(dir(&ignore, "x/y/cache"), Some(true));
+        assert_eq!(file(&ignore, "logs/today/1.txt"), Some(true));
+        // Only what is inside, not the directory itself.
+        assert_eq!(dir(&ignore, "logs"), None);
+        assert_eq!(file(&ignore, "a/b"), Some(true));
+        assert_eq!(file(&ignore, "a/x/y/b"), Some(true));
+        assert_eq!(file(&ignore, "a/x/y/c"), None);
+    }
+
+    #[test]
+    fn negation_overrides_earlier_patterns() {
+        let ignore = root("*.log\n!keep.log\n");
+        assert_eq!(file(&ignore, "other.log"), Some(true));
+        assert_eq!(file(&ignore, "keep.log"), Some(false));
+        assert!(!is_ignored(&[ignore], Path::new("src/keep.log"), false));
+
+        // The last matching pattern wins, so a later pattern ignores it again.
+        let ignore = root("!keep.log\n*.log\n");
+        assert_eq!(file(&ignore, "keep.log"), Some(true));
+    }
+
+    #[test]
+    fn directory_only_patterns() {
+        let ignore = root("out/\n/dist/\n");
+        assert_eq!(dir(&ignore, "out"), Some(true));
+        assert_eq!(dir(&ignore, "src/out"), Some(true));
+        assert_eq!(file(&ignore, "out"), None);
+        assert_eq!(dir(&ignore, "dist"), Some(true));
+        assert_eq!(dir(&ignore, "src/dist"), None);
+    }
+
+    #[test]
+    fn character_classes_and_escapes() {
+        let ignore = root("file[0-9].rs\nv?.txt\n[!a]b\n\\#notes\n\\!bang\n");
+        assert_eq!(file(&ignore, "file7.rs"), Some(true));
+        assert_eq!(file(&ignore, "filex.rs"), None);
+        assert_eq!(file(&ignore, "v1.txt"), Some(true));
+        assert_eq!(file(&ignore, "v10.txt"), None);
+        assert_eq!(file(&ignore, "cb"), Some(true));
+        assert_eq!(file(&ignore, "ab"), None);
+        assert_eq!(file(&ignore, "#notes"), Some(true));
+        assert_eq!(file(&ignore, "!bang"), Some(true));
+    }
+
+    #[test]
+    fn nested_gitignores_apply_below_their_directory() {
+        let ignores = [
+            root("*.tmp\n"),
+            Gitignore::parse(PathBuf::from("sub"), "!keep.tmp\n/local\n"),
+        ];
+        assert!(is_ignored(&ignores, Path::new("keep.tmp"), false));
+        assert!(is_ignored(&ignores, Path::new("sub/other.tmp"), false));
+        // The deeper file re-includes what the root ignores.
+        assert!(!is_ignored(&ignores, Path::new("sub/keep.tmp"), false));
+        // Anchored to `sub`, not to the root.
+        assert!(is_ignored(&ignores, Path::new("sub/local"), false));
+        assert!(!is_ignored(&ignores, Path::new("local"), false));
+        assert!(!is_ignored(&ignores, Path::new("sub/x/local"), false));
+    }
+}
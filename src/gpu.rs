@@ -0,0 +1,64 @@
+//! Finding a graphics adapter to draw with, and the format to draw in.
+
+const NO_ADAPTER: &str = "no graphics adapter found. brewcode draws with Vulkan, so it needs a \
+                          Vulkan driver, such as Mesa's lavapipe on machines without a GPU";
+
+/// Requests an adapter, which may be a software one when there is no GPU. Fails with a message
+/// for the user when there is none at all.
+pub fn request_adapter() -> Result<wgpu::Adapter, String> {
+    // wgpu aborts the whole process rather than returning `None` when there are no adapters,
+    // so look for one first.
+    if !adapter_exists() {
+        return Err(NO_ADAPTER.to_owned());
+    }
+    wgpu::Adapter::request(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::Default,
+        backends: wgpu::BackendBit::PRIMARY,
+    })
+    .ok_or_else(|| NO_ADAPTER.to_owned())
+}
+
+/// Whether the Vulkan loader is installed and knows of at least one device.
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+fn adapter_exists() -> bool {
+    use ash::version::{EntryV1_0, InstanceV1_0};
+
+    let entry = match ash::Entry::new() {
+        Ok(entry) => entry,
+        Err(_) => return false,
+    };
+    let info = ash::vk::InstanceCreateInfo::default();
+    // SAFETY: `info` is a valid, empty create info that lives for the call.
+    let instance = match unsafe { entry.create_instance(&info, None) } {
+        Ok(instance) => instance,
+        Err(_) => return false,
+    };
+    // SAFETY: `instance` was just created and is only destroyed below, once it's no longer used
+    // and with nothing created from it still alive.
+    unsafe {
+        let exists = instance
+            .enumerate_physical_devices()
+            .is_ok_and(|devices| !devices.is_empty());
+        instance.destroy_instance(None);
+        exists
+    }
+}
+
+/// The format windows are drawn in. Every Vulkan, Metal and DirectX driver wgpu runs on can
+/// present it, and being sRGB it blends and anti-aliases in linear space.
+pub const WINDOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+/// Whether colours written to `format` are encoded to sRGB on the way, so they need to be
+/// given in linear space.
+pub fn is_srgb(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Bgra8UnormSrgb | wgpu::TextureFormat::Rgba8UnormSrgb
+    )
+}
+
+/// Metal and DirectX 11 are always there on the other platforms.
+#[cfg(not(all(unix, not(any(target_os = "macos", target_os = "ios")))))]
+fn adapter_exists() -> bool {
+    true
+}
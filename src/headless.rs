@@ -0,0 +1,112 @@
+//! Drawing the editor into an image instead of a window, so how it looks can be checked without
+//! a screen.
+
+use crate::cli::Args;
+use crate::editor::Editor;
+use crate::renderer::Renderer;
+use crate::{build_glyph_brush, gpu, png};
+use std::{cell::RefCell, path::Path, rc::Rc};
+use winit::dpi::PhysicalSize;
+
+/// Texture copies need every row of the buffer to start at a multiple of this.
+const ROW_ALIGNMENT: u32 = 256;
+
+/// Draws one frame of an editor opened with `args` and writes it to a PNG at `path`.
+///
+/// The editor is opened hermetically, see [`Editor::hermetic`], so the same arguments always give
+/// the same picture.
+pub fn render_to_png(args: &Args, path: &Path, (width, height): (u32, u32)) -> Result<(), String> {
+    let adapter = gpu::request_adapter()?;
+    let (mut device, mut queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+        extensions: wgpu::Extensions {
+            anisotropic_filtering: false,
+        },
+        limits: wgpu::Limits::default(),
+    });
+
+    // The channels come out in the order PNG wants them.
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size,
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+    });
+    let view = texture.create_default_view();
+
+    let mut editor = Editor::hermetic(PhysicalSize::new(width, height), args);
+    let glyph_brush = build_glyph_brush(&mut editor, &mut device, format);
+    let mut renderer = Renderer::new(&device, format, glyph_brush);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+    editor.draw(&mut renderer);
+    renderer.render(
+        &mut device,
+        &mut encoder,
+        &view,
+        (width, height),
+        editor.config().colors.background,
+    )?;
+
+    let row_pitch = width
+        .checked_mul(4)
+        .and_then(|bytes| bytes.checked_next_multiple_of(ROW_ALIGNMENT))
+        .ok_or_else(|| format!("{}x{} is too large to draw", width, height))?;
+    let buffer_size = row_pitch as u64 * height as u64;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        size: buffer_size,
+        usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: wgpu::Origin3d {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        },
+        wgpu::BufferCopyView {
+            buffer: &buffer,
+            offset: 0,
+            row_pitch,
+            image_height: height,
+        },
+        size,
+    );
+    queue.submit(&[encoder.finish()]);
+
+    let mapped = Rc::new(RefCell::new(None));
+    let result = Rc::clone(&mapped);
+    buffer.map_read_async(
+        0,
+        buffer_size,
+        move |mapping: wgpu::BufferMapAsyncResult<&[u8]>| {
+            *result.borrow_mut() = Some(mapping.map(|mapping| mapping.data.to_vec()));
+        },
+    );
+    // Waits for the frame to be drawn and copied, which calls back with the pixels.
+    device.poll(true);
+    let padded = match mapped.borrow_mut().take() {
+        Some(Ok(padded)) => padded,
+        _ => return Err("failed to read the frame back from the GPU".to_owned()),
+    };
+
+    let pixels: Vec<u8> = padded
+        .chunks(row_pitch as usize)
+        .flat_map(|row| &row[..width as usize * 4])
+        .copied()
+        .collect();
+    png::write(path, width, height, &pixels)
+        .map_err(|error| format!("failed to write {}: {}", path.display(), error))
+}
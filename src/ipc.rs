@@ -0,0 +1,208 @@
+//! Talking to the instance of brewcode that is already running, so files named on the command
+//! line open in its window rather than in a new one.
+//!
+//! A client connects to the socket and sends a request as one line of JSON. The running
+//! instance answers "opened" once the files are open and, if the client asked to wait, "closed"
+//! once they are all closed again.
+
+use crate::cli::{Args, FileArg, Source};
+use crate::dirs;
+use serde_json::{json, Value};
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+/// How long a client gets to send its request before it is hung up on.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn socket_path() -> Option<PathBuf> {
+    Some(dirs::runtime_dir()?.join("brewcode.sock"))
+}
+
+/// Files to open, sent by another brewcode process.
+pub struct OpenRequest {
+    /// Paths are absolute, as the client may run in another directory.
+    pub files: Vec<FileArg>,
+    pub readonly: bool,
+    pub syntax: Option<String>,
+    /// Whether the client waits for the files to be closed.
+    pub wait: bool,
+    pub client: Client,
+}
+
+/// The process that sent a request, listening for answers.
+pub struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    /// Tells the client its files are open.
+    pub fn send_opened(&mut self) {
+        // The client might have given up already, and then there is nobody to tell.
+        let _ = self.stream.write_all(b"opened\n");
+    }
+
+    /// Tells the client its files were closed, which lets it exit.
+    pub fn send_closed(mut self) {
+        let _ = self.stream.write_all(b"closed\n");
+    }
+}
+
+/// Listens for requests from other instances on a background thread.
+pub struct Server {
+    path: PathBuf,
+    receiver: Receiver<OpenRequest>,
+}
+
+impl Server {
+    /// Starts listening, calling `notify` from a background thread whenever a request is ready
+    /// to be picked up with `poll`. Fails with `AddrInUse` if another instance is listening.
+    pub fn start(notify: impl Fn() + Clone + Send + 'static) -> io::Result<Self> {
+        let path = socket_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no runtime directory"))?;
+        if is_running() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another instance is running",
+            ));
+        }
+        // Left behind by an instance that didn't exit cleanly.
+        match fs::remove_file(&path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                // Each client gets a thread of its own, so one that is slow to send its request
+                // doesn't hold up the others.
+                let sender = sender.clone();
+                let notify = notify.clone();
+                thread::spawn(move || {
+                    if let Some(request) = read_request(stream) {
+                        if sender.send(request).is_ok() {
+                            notify();
+                        }
+                    }
+                });
+            }
+        });
+        Ok(Self { path, receiver })
+    }
+
+    /// Takes the requests that came in since the last call.
+    pub fn poll(&mut self) -> Vec<OpenRequest> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Whether another instance is listening for requests.
+pub fn is_running() -> bool {
+    socket_path().is_some_and(|path| UnixStream::connect(path).is_ok())
+}
+
+fn read_request(stream: UnixStream) -> Option<OpenRequest> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).ok()?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).ok()?;
+    let value: Value = serde_json::from_str(&line).ok()?;
+    // The stream goes back to blocking so a waiting client can sit quietly for as long as it
+    // takes.
+    stream.set_read_timeout(None).ok()?;
+
+    let files = value
+        .get("files")?
+        .as_array()?
+        .iter()
+        .map(|file| {
+            let line = file.get("line").and_then(Value::as_u64);
+            let column = file.get("column").and_then(Value::as_u64);
+            Some(FileArg {
+                source: Source::Path(PathBuf::from(file.get("path")?.as_str()?)),
+                position: line.map(|line| (line as usize, column.map(|column| column as usize))),
+            })
+        })
+        .collect::<Option<_>>()?;
+    Some(OpenRequest {
+        files,
+        readonly: value.get("readonly").and_then(Value::as_bool)?,
+        syntax: value
+            .get("syntax")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        wait: value.get("wait").and_then(Value::as_bool)?,
+        client: Client { stream },
+    })
+}
+
+/// Asks the running instance to open the files in `args`, waiting for them to be closed again
+/// with `--wait`. Returns false if no instance is running.
+pub fn open_in_running(args: &Args) -> io::Result<bool> {
+    let path = match socket_path() {
+        Some(path) => path,
+        None => return Ok(false),
+    };
+    let mut stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(_) => return Ok(false),
+    };
+
+    let dir = env::current_dir()?;
+    let files: Vec<Value> = args
+        .files
+        .iter()
+        .filter_map(|file| match &file.source {
+            Source::Path(path) => Some(json!({
+                "path": dir.join(path).to_string_lossy(),
+                "line": file.position.map(|(line, _)| line),
+                "column": file.position.and_then(|(_, column)| column),
+            })),
+            // The caller keeps standard input to itself.
+            Source::Stdin => None,
+        })
+        .collect();
+    let request = json!({
+        "files": files,
+        "readonly": args.readonly,
+        "syntax": args.syntax,
+        "wait": args.wait,
+    });
+    writeln!(stream, "{}", request)?;
+
+    let mut replies = BufReader::new(stream).lines();
+    match replies.next().transpose()? {
+        Some(reply) if reply == "opened" => {}
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the running instance didn't open the files",
+            ))
+        }
+    }
+    if args.wait {
+        // The instance closing counts as the files being closed too.
+        for reply in replies {
+            if reply? == "closed" {
+                break;
+            }
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,494 @@
+use crate::command::Command;
+use std::fmt;
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// Letters are always lowercase; Shift is a modifier.
+    Char(char),
+    F(u8),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Escape,
+    Tab,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+}
+
+// Names of keys that aren't written as the character they type.
+const KEY_NAMES: &[(Key, &str)] = &[
+    (Key::Up, "Up"),
+    (Key::Down, "Down"),
+    (Key::Left, "Left"),
+    (Key::Right, "Right"),
+    (Key::Home, "Home"),
+    (Key::End, "End"),
+    (Key::PageUp, "PageUp"),
+    (Key::PageDown, "PageDown"),
+    (Key::Enter, "Enter"),
+    (Key::Escape, "Escape"),
+    (Key::Tab, "Tab"),
+    (Key::Space, "Space"),
+    (Key::Backspace, "Backspace"),
+    (Key::Delete, "Delete"),
+    (Key::Insert, "Insert"),
+];
+
+impl Key {
+    fn parse(text: &str) -> Option<Self> {
+        if let Some(&(key, _)) = KEY_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(text))
+        {
+            return Some(key);
+        }
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_graphic() => Some(Key::Char(c.to_ascii_lowercase())),
+            (Some('f'), Some(_)) | (Some('F'), Some(_)) => match text[1..].parse() {
+                Ok(number) if (1..=24).contains(&number) => Some(Key::F(number)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            Key::F(number) => write!(f, "F{}", number),
+            key => {
+                let (_, name) = KEY_NAMES.iter().find(|(named, _)| named == key).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// A key together with the modifiers held down, e.g. Ctrl+Shift+S.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl KeyPress {
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    /// Parses text like "ctrl+shift+s". Case doesn't matter.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // "ctrl++" binds the plus key.
+        if text.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let (key, modifiers) = parts.split_last()?;
+        let mut press = Self::new(Key::parse(key.trim())?);
+        for modifier in modifiers {
+            match modifier.trim().to_ascii_lowercase().as_str() {
+                "ctrl" => press.ctrl = true,
+                "alt" => press.alt = true,
+                "shift" => press.shift = true,
+                _ => return None,
+            }
+        }
+        Some(press)
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// One or more key presses in a row, e.g. Ctrl+K Ctrl+C.
+pub type Chord = Vec<KeyPress>;
+
+/// Parses key presses separated by spaces, e.g. "ctrl+k ctrl+c".
+pub fn parse_chord(text: &str) -> Option<Chord> {
+    let chord = text
+        .split_whitespace()
+        .map(KeyPress::parse)
+        .collect::<Option<Chord>>()?;
+    if chord.is_empty() {
+        None
+    } else {
+        Some(chord)
+    }
+}
+
+pub fn chord_to_string(chord: &[KeyPress]) -> String {
+    chord
+        .iter()
+        .map(KeyPress::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyResult {
+    Command(Command),
+    /// The key started or continued a chord; more keys are needed.
+    Pending,
+    /// The key didn't complete the chord in progress, so the chord, including this key, was
+    /// dropped.
+    Cancelled(Chord),
+    /// Nothing is bound to the key.
+    Unbound,
+}
+
+/// Maps chords to commands and keeps track of the chord being typed.
+pub struct Keymap {
+    bindings: Vec<(Chord, Command)>,
+    pending: Chord,
+}
+
+const DEFAULT_BINDINGS: &[(&str, Command)] = &[
+    ("ctrl+shift+p", Command::ShowCommands),
+    ("ctrl+p", Command::FindFile),
+    ("ctrl+b", Command::ToggleExplorer),
+    ("ctrl+shift+e", Command::FocusExplorer),
+    ("ctrl+alt+n", Command::NewFile),
+    ("ctrl+k ctrl+n", Command::NewFolder),
+    ("ctrl+k ctrl+r", Command::RenameEntry),
+    ("ctrl+k ctrl+d", Command::DeleteEntry),
+    ("ctrl+q", Command::Quit),
+    ("ctrl+s", Command::Save),
+    ("ctrl+shift+s", Command::SaveAs),
+    ("f2", Command::Rename),
+    ("ctrl+alt+l", Command::UseLf),
+    ("ctrl+alt+r", Command::UseCrLf),
+    ("ctrl+alt+a", Command::ToggleAutosave),
+    ("ctrl+z", Command::Undo),
+    ("ctrl+shift+z", Command::Redo),
+    ("ctrl+y", Command::Redo),
+    ("ctrl+\\", Command::SplitHorizontal),
+    ("ctrl+shift+\\", Command::SplitVertical),
+    ("ctrl+w", Command::ClosePane),
+    ("alt+left", Command::FocusLeft),
+    ("alt+right", Command::FocusRight),
+    ("alt+up", Command::FocusUp),
+    ("alt+down", Command::FocusDown),
+    ("left", Command::CursorLeft),
+    ("right", Command::CursorRight),
+    ("up", Command::CursorUp),
+    ("down", Command::CursorDown),
+    ("ctrl+g", Command::GoToLine),
+    ("ctrl+shift+o", Command::GoToSymbol),
+];
+
+impl Keymap {
+    /// The default bindings with `overrides` applied in order. An override without a command
+    /// unbinds the chord.
+    pub fn new(overrides: &[(Chord, Option<Command>)]) -> Self {
+        let mut bindings: Vec<(Chord, Command)> = DEFAULT_BINDINGS
+            .iter()
+            .map(|&(chord, command)| (parse_chord(chord).unwrap(), command))
+            .collect();
+        for (chord, command) in overrides {
+            bindings.retain(|(bound, _)| bound != chord);
+            if let Some(command) = command {
+                bindings.push((chord.clone(), *command));
+            }
+        }
+        Self {
+            bindings,
+            pending: vec![],
+        }
+    }
+
+    /// Feeds a key press in, returning what it did.
+    pub fn press(&mut self, press: KeyPress) -> KeyResult {
+        let was_pending = !self.pending.is_empty();
+        self.pending.push(press);
+
+        if let Some((_, command)) = self
+            .bindings
+            .iter()
+            .find(|(chord, _)| *chord == self.pending)
+        {
+            self.pending.clear();
+            return KeyResult::Command(*command);
+        }
+        let pending = &self.pending;
+        if self
+            .bindings
+            .iter()
+            .any(|(chord, _)| chord.starts_with(pending))
+        {
+            return KeyResult::Pending;
+        }
+
+        let chord = std::mem::take(&mut self.pending);
+        if was_pending {
+            KeyResult::Cancelled(chord)
+        } else {
+            KeyResult::Unbound
+        }
+    }
+
+    /// The chords bound to `command`.
+    pub fn chords_for(&self, command: Command) -> impl Iterator<Item = &Chord> {
+        self.bindings
+            .iter()
+            .filter(move |(_, bound)| *bound == command)
+            .map(|(chord, _)| chord)
+    }
+
+    /// The keys of the chord typed so far, if one is in progress.
+    pub fn pending(&self) -> &[KeyPress] {
+        &self.pending
+    }
+
+    /// Drops the chord in progress, e.g. when a prompt takes over the keyboard.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Converts a key from winit. Keys the keymap can't name give `None`.
+pub fn from_winit(keycode: VirtualKeyCode, modifiers: ModifiersState) -> Option<KeyPress> {
+    use VirtualKeyCode::*;
+
+    let key = match keycode {
+        A => Key::Char('a'),
+        B => Key::Char('b'),
+        C => Key::Char('c'),
+        D => Key::Char('d'),
+        E => Key::Char('e'),
+        F => Key::Char('f'),
+        G => Key::Char('g'),
+        H => Key::Char('h'),
+        I => Key::Char('i'),
+        J => Key::Char('j'),
+        K => Key::Char('k'),
+        L => Key::Char('l'),
+        M => Key::Char('m'),
+        N => Key::Char('n'),
+        O => Key::Char('o'),
+        P => Key::Char('p'),
+        Q => Key::Char('q'),
+        R => Key::Char('r'),
+        S => Key::Char('s'),
+        T => Key::Char('t'),
+        U => Key::Char('u'),
+        V => Key::Char('v'),
+        W => Key::Char('w'),
+        X => Key::Char('x'),
+        Y => Key::Char('y'),
+        Z => Key::Char('z'),
+        Key0 | Numpad0 => Key::Char('0'),
+        Key1 | Numpad1 => Key::Char('1'),
+        Key2 | Numpad2 => Key::Char('2'),
+        Key3 | Numpad3 => Key::Char('3'),
+        Key4 | Numpad4 => Key::Char('4'),
+        Key5 | Numpad5 => Key::Char('5'),
+        Key6 | Numpad6 => Key::Char('6'),
+        Key7 | Numpad7 => Key::Char('7'),
+        Key8 | Numpad8 => Key::Char('8'),
+        Key9 | Numpad9 => Key::Char('9'),
+        Apostrophe => Key::Char('\''),
+        Backslash => Key::Char('\\'),
+        Comma | NumpadComma => Key::Char(','),
+        Equals | NumpadEquals => Key::Char('='),
+        Grave => Key::Char('`'),
+        LBracket => Key::Char('['),
+        Minus | Subtract => Key::Char('-'),
+        Period | Decimal => Key::Char('.'),
+        RBracket => Key::Char(']'),
+        Semicolon => Key::Char(';'),
+        Slash | Divide => Key::Char('/'),
+        Add => Key::Char('+'),
+        Multiply => Key::Char('*'),
+        F1 => Key::F(1),
+        F2 => Key::F(2),
+        F3 => Key::F(3),
+        F4 => Key::F(4),
+        F5 => Key::F(5),
+        F6 => Key::F(6),
+        F7 => Key::F(7),
+        F8 => Key::F(8),
+        F9 => Key::F(9),
+        F10 => Key::F(10),
+        F11 => Key::F(11),
+        F12 => Key::F(12),
+        Up => Key::Up,
+        Down => Key::Down,
+        Left => Key::Left,
+        Right => Key::Right,
+        Home => Key::Home,
+        End => Key::End,
+        PageUp => Key::PageUp,
+        PageDown => Key::PageDown,
+        Return | NumpadEnter => Key::Enter,
+        Escape => Key::Escape,
+        Tab => Key::Tab,
+        Space => Key::Space,
+        Back => Key::Backspace,
+        Delete => Key::Delete,
+        Insert => Key::Insert,
+        _ => return None,
+    };
+    Some(KeyPress {
+        key,
+        ctrl: modifiers.ctrl(),
+        alt: modifiers.alt(),
+        shift: modifiers.shift(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(text: &str) -> KeyPress {
+        KeyPress::parse(text).unwrap()
+    }
+
+    fn chord(text: &str) -> Chord {
+        parse_chord(text).unwrap()
+    }
+
+    #[test]
+    fn two_key_chord() {
+        let mut keymap = Keymap::new(&[]);
+        assert_eq!(keymap.press(press("ctrl+k")), KeyResult::Pending);
+        assert_eq!(keymap.pending(), &[press("ctrl+k")][..]);
+        assert_eq!(
+            keymap.press(press("ctrl+n")),
+            KeyResult::Command(Command::NewFolder)
+        );
+        assert!(keymap.pending().is_empty());
+    }
+
+    #[test]
+    fn wrong_second_key_cancels_the_chord() {
+        let mut keymap = Keymap::new(&[]);
+        keymap.press(press("ctrl+k"));
+        assert_eq!(
+            keymap.press(press("x")),
+            KeyResult::Cancelled(chord("ctrl+k x"))
+        );
+        assert!(keymap.pending().is_empty());
+        // The next key starts over.
+        assert_eq!(
+            keymap.press(press("ctrl+s")),
+            KeyResult::Command(Command::Save)
+        );
+    }
+
+    #[test]
+    fn unbound_key() {
+        let mut keymap = Keymap::new(&[]);
+        assert_eq!(keymap.press(press("ctrl+alt+shift+f9")), KeyResult::Unbound);
+        assert!(keymap.pending().is_empty());
+    }
+
+    #[test]
+    fn overrides() {
+        let mut keymap = Keymap::new(&[
+            (chord("ctrl+s"), Some(Command::Quit)),
+            (chord("alt+x alt+y"), Some(Command::Save)),
+        ]);
+        assert_eq!(
+            keymap.press(press("ctrl+s")),
+            KeyResult::Command(Command::Quit)
+        );
+        assert_eq!(keymap.press(press("alt+x")), KeyResult::Pending);
+        assert_eq!(
+            keymap.press(press("alt+y")),
+            KeyResult::Command(Command::Save)
+        );
+        assert_eq!(
+            keymap.chords_for(Command::Save).collect::<Vec<_>>(),
+            vec![&chord("alt+x alt+y")]
+        );
+    }
+
+    #[test]
+    fn unbinding() {
+        let mut keymap = Keymap::new(&[(chord("ctrl+s"), None)]);
+        assert_eq!(keymap.press(press("ctrl+s")), KeyResult::Unbound);
+        assert_eq!(keymap.chords_for(Command::Save).count(), 0);
+        // Later overrides win.
+        let mut keymap = Keymap::new(&[
+            (chord("ctrl+s"), None),
+            (chord("ctrl+s"), Some(Command::SaveAs)),
+        ]);
+        assert_eq!(
+            keymap.press(press("ctrl+s")),
+            KeyResult::Command(Command::SaveAs)
+        );
+    }
+
+    #[test]
+    fn parse_chord_edge_cases() {
+        let plus = KeyPress {
+            ctrl: true,
+            ..KeyPress::new(Key::Char('+'))
+        };
+        assert_eq!(parse_chord("ctrl++"), Some(vec![plus]));
+        assert_eq!(
+            parse_chord("Ctrl+Shift+P"),
+            Some(vec![KeyPress {
+                ctrl: true,
+                shift: true,
+                ..KeyPress::new(Key::Char('p'))
+            }])
+        );
+        assert_eq!(
+            parse_chord("ctrl+k  ctrl+c").map(|chord| chord.len()),
+            Some(2)
+        );
+        assert_eq!(parse_chord("f13"), Some(vec![KeyPress::new(Key::F(13))]));
+        assert_eq!(
+            parse_chord("pagedown"),
+            Some(vec![KeyPress::new(Key::PageDown)])
+        );
+
+        assert_eq!(parse_chord("hyper+a"), None);
+        assert_eq!(parse_chord("ctrl+"), None);
+        assert_eq!(parse_chord("f25"), None);
+        assert_eq!(parse_chord("ctrl+enterr"), None);
+        assert_eq!(parse_chord(""), None);
+        assert_eq!(parse_chord("   "), None);
+    }
+
+    #[test]
+    fn chords_print_the_way_they_parse() {
+        let text = chord_to_string(&chord("ctrl+shift+k alt+f4"));
+        assert_eq!(text, "Ctrl+Shift+K Alt+F4");
+        assert_eq!(parse_chord(&text), Some(chord("ctrl+shift+k alt+f4")));
+    }
+}
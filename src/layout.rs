@@ -0,0 +1,85 @@
+use wgpu_glyph::{GlyphBrush, Point, Scale};
+use winit::dpi::PhysicalPosition;
+
+/// An axis-aligned region of the window in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width: width.max(0.0),
+            height: height.max(0.0),
+        }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, position: PhysicalPosition<i32>) -> bool {
+        let (x, y) = (position.x as f32, position.y as f32);
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// The part of `self` inside `other`, which is empty if they don't overlap.
+    pub fn intersection(&self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        Rect::new(
+            x,
+            y,
+            self.right().min(other.right()) - x,
+            self.bottom().min(other.bottom()) - y,
+        )
+    }
+
+    /// Splits off a strip of `height` pixels from the top, returning `(top, rest)`.
+    pub fn split_top(&self, height: f32) -> (Rect, Rect) {
+        let height = height.min(self.height);
+        (
+            Rect::new(self.x, self.y, self.width, height),
+            Rect::new(self.x, self.y + height, self.width, self.height - height),
+        )
+    }
+
+    /// Splits off a strip of `width` pixels from the left, returning `(left, rest)`.
+    pub fn split_left(&self, width: f32) -> (Rect, Rect) {
+        let width = width.min(self.width);
+        (
+            Rect::new(self.x, self.y, width, self.height),
+            Rect::new(self.x + width, self.y, self.width - width, self.height),
+        )
+    }
+
+    /// Splits off a strip of `height` pixels from the bottom, returning `(rest, bottom)`.
+    pub fn split_bottom(&self, height: f32) -> (Rect, Rect) {
+        let height = height.min(self.height);
+        (
+            Rect::new(self.x, self.y, self.width, self.height - height),
+            Rect::new(self.x, self.bottom() - height, self.width, height),
+        )
+    }
+}
+
+/// Horizontal advance of `text` when drawn with the first font at `scale`.
+pub fn text_width(glyph_brush: &GlyphBrush<()>, text: &str, scale: f32) -> f32 {
+    glyph_brush
+        .fonts()
+        .first()
+        .unwrap()
+        .layout(text, Scale::uniform(scale), Point { x: 0.0, y: 0.0 })
+        .map(|glyph| glyph.unpositioned().h_metrics().advance_width)
+        .sum()
+}
@@ -4,37 +4,185 @@
 // * Support mouse up/down/move in editor/buffer
 // * Add layout functionality
 
-mod buffer;
+mod cli;
+mod command;
+mod config;
+mod diff;
+mod dirs;
+mod document;
 mod editor;
+mod encoding;
+mod explorer;
+mod file_index;
+mod fuzzy;
+mod gitignore;
+mod gpu;
+mod headless;
+#[cfg(unix)]
+mod ipc;
+mod keymap;
+mod layout;
+mod notification;
+mod palette;
+mod pane;
+mod png;
+mod prompt;
+mod recovery;
 mod rectangle_brush;
-
-use editor::Editor;
-use rectangle_brush::RectangleBrush;
-
-use wgpu_glyph::{GlyphBrushBuilder, Scale, Section};
+mod renderer;
+mod session;
+mod status_bar;
+mod tab_bar;
+mod toml;
+mod view;
+
+use cli::{Parsed, Source};
+use config::PresentMode;
+use editor::{Editor, UserEvent};
+use layout::text_width;
+use renderer::Renderer;
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use wgpu_glyph::{Font, GlyphBrush, GlyphBrushBuilder};
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, Event, ModifiersState, MouseScrollDelta, VirtualKeyCode, WindowEvent},
+    event::{DeviceEvent, Event, ModifiersState, MouseScrollDelta, StartCause, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::{CursorIcon, WindowBuilder},
 };
 
+/// How often open files are checked for changes made by other programs, and unsaved changes are
+/// journaled for crash recovery.
+const DISK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const BUNDLED_FONT: &[u8] = include_bytes!("../res/UbuntuMono-R.ttf");
+
+/// The font at `path`, or the bundled one without a path.
+fn load_font(path: Option<&Path>) -> Result<Font<'static>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(Font::from_bytes(BUNDLED_FONT).expect("Bundled font is invalid.")),
+    };
+    let bytes = std::fs::read(path)
+        .map_err(|error| format!("Failed to read font {}: {}", path.display(), error))?;
+    Font::from_bytes(bytes).map_err(|error| format!("Invalid font {}: {}", path.display(), error))
+}
+
+fn build_glyph_brush(
+    editor: &mut Editor,
+    device: &mut wgpu::Device,
+    render_format: wgpu::TextureFormat,
+) -> GlyphBrush<'static, ()> {
+    let font = load_font(editor.config().font.as_deref()).unwrap_or_else(|error| {
+        editor.report_error(error);
+        load_font(None).unwrap()
+    });
+    let glyph_brush = GlyphBrushBuilder::using_font(font).build(device, render_format);
+    // Hit-testing assumes a monospace font, so one character is enough to measure.
+    editor.set_char_width(text_width(&glyph_brush, "M", 100.0) / 100.0);
+    glyph_brush
+}
+
+/// Creates a swap chain for a window of `size`, or `None` while the window has no area, e.g.
+/// when minimized, as there is nothing to draw then.
+fn create_swap_chain(
+    device: &wgpu::Device,
+    surface: &wgpu::Surface,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_format: wgpu::TextureFormat,
+    present_mode: PresentMode,
+) -> Option<wgpu::SwapChain> {
+    if size.width == 0 || size.height == 0 {
+        return None;
+    }
+    Some(device.create_swap_chain(
+        surface,
+        &wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: render_format,
+            width: size.width,
+            height: size.height,
+            present_mode: match present_mode {
+                PresentMode::Vsync => wgpu::PresentMode::Vsync,
+                PresentMode::Immediate => wgpu::PresentMode::NoVsync,
+            },
+        },
+    ))
+}
+
+/// Size of the frame drawn with `--render-to` unless `--size` says otherwise.
+const DEFAULT_RENDER_SIZE: (u32, u32) = (800, 600);
+
+/// Reports a problem that keeps brewcode from starting.
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("brewcode: {}", message);
+    std::process::exit(1);
+}
+
+/// Reports a problem with the command line the way command line tools do.
+fn exit_with_usage_error(message: &str) -> ! {
+    eprintln!("brewcode: {}", message);
+    eprintln!("Try 'brewcode --help' for more information.");
+    std::process::exit(2);
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut args = std::env::args();
-    let file_name = args.nth(1).expect("Must specify a file to open");
+    let args = match cli::parse(std::env::args_os().skip(1)) {
+        Ok(Parsed::Run(args)) => args,
+        Ok(Parsed::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(Parsed::Version) => {
+            println!("brewcode {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(message) => exit_with_usage_error(&message),
+    };
+    if let Some(syntax) = &args.syntax {
+        if !document::syntax_exists(syntax) {
+            exit_with_usage_error(&format!("unknown syntax {}", syntax));
+        }
+    }
+    if let Some(path) = &args.config {
+        if !path.is_file() {
+            exit_with_usage_error(&format!("no config file at {}", path.display()));
+        }
+    }
+
+    if let Some(path) = &args.render_to {
+        let size = args.size.unwrap_or(DEFAULT_RENDER_SIZE);
+        if let Err(message) = headless::render_to_png(&args, path, size) {
+            exit_with_error(&message);
+        }
+        return Ok(());
+    }
+
+    // Hand the files over to the instance that is already running, if there is one. Standard
+    // input can't be handed over, and a different config needs a window of its own.
+    #[cfg(unix)]
+    {
+        let stdin = args.files.iter().any(|file| file.source == Source::Stdin);
+        if !args.files.is_empty() && !stdin && !args.new_window && args.config.is_none() {
+            match ipc::open_in_running(&args) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(error) => eprintln!("brewcode: {}, opening a new window", error),
+            }
+        }
+    }
 
-    let event_loop = EventLoop::new();
+    let event_loop = EventLoop::<UserEvent>::with_user_event();
     let window = WindowBuilder::new()
         .with_title("brewcode")
         .build(&event_loop)?;
     let mut size = window.inner_size();
     let surface = wgpu::Surface::create(&window);
 
-    let adapter = wgpu::Adapter::request(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::Default,
-        backends: wgpu::BackendBit::PRIMARY,
-    })
-    .expect("Failed to create adapter");
+    let adapter = gpu::request_adapter().unwrap_or_else(|message| exit_with_error(&message));
 
     let (mut device, mut queue) = adapter.request_device(&wgpu::DeviceDescriptor {
         extensions: wgpu::Extensions {
@@ -43,66 +191,91 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         limits: wgpu::Limits::default(),
     });
 
-    // TODO: Select supported render format instead of hard-coding.
-    let render_format = wgpu::TextureFormat::Bgra8UnormSrgb;
+    let render_format = gpu::WINDOW_FORMAT;
 
-    let mut swap_chain = device.create_swap_chain(
-        &surface,
-        &wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-            format: render_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Vsync,
-        },
-    );
+    let mut editor = Editor::new(size, &args);
+    editor.set_event_proxy(event_loop.create_proxy());
+    #[cfg(unix)]
+    editor.start_ipc_server();
 
-    // TODO: Dynamically load fonts or something?
-    let inconsolata: &[u8] = include_bytes!("../res/UbuntuMono-R.ttf");
-    // /Users/connor/Library/Fonts/InconsolataGo-Regular.ttf");
-    let mut glyph_brush =
-        GlyphBrushBuilder::using_font_bytes(inconsolata).build(&mut device, render_format);
+    let mut present_mode = editor.config().present_mode;
+    // Created when a frame is drawn, and dropped whenever it no longer fits the window.
+    let mut swap_chain = None;
+    // What went wrong drawing the last frame, if anything.
+    let mut render_error: Option<String> = None;
 
-    let mut rectangle_brush = RectangleBrush::new(&device, render_format);
+    let mut font = editor.config().font.clone();
+    let glyph_brush = build_glyph_brush(&mut editor, &mut device, render_format);
+    let mut renderer = Renderer::new(&device, render_format, glyph_brush);
 
-    window.request_redraw();
     window.set_cursor_icon(CursorIcon::Text);
 
-    let mut editor = Editor::new(size, file_name);
-    let mut last_frame = std::time::Instant::now();
+    let mut window_title = editor.window_title();
+    window.set_title(&window_title);
+    let mut next_disk_poll = Instant::now() + DISK_POLL_INTERVAL;
 
-    let mut modifier_pressed = false;
+    let mut modifiers = ModifiersState::default();
     let mut cursor_position: PhysicalPosition<i32> = PhysicalPosition::new(0, 0);
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
             event: WindowEvent::CloseRequested,
             ..
-        } => *control_flow = ControlFlow::Exit,
+        } => {
+            editor.request_quit();
+            if editor.should_quit() {
+                *control_flow = ControlFlow::Exit;
+            }
+        }
+
+        Event::UserEvent(event) => editor.handle_user_event(event),
+
+        Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+            editor.check_disk_changes();
+            editor.journal_unsaved();
+            editor.check_explorer_changes();
+
+            if editor.check_config_changes() {
+                if editor.config().font != font {
+                    font = editor.config().font.clone();
+                    let glyph_brush = build_glyph_brush(&mut editor, &mut device, render_format);
+                    renderer.set_glyph_brush(glyph_brush);
+                }
+                if editor.config().present_mode != present_mode {
+                    present_mode = editor.config().present_mode;
+                    swap_chain = None;
+                }
+            }
+            editor.expire_status_message();
+            next_disk_poll = Instant::now() + DISK_POLL_INTERVAL;
+            *control_flow = ControlFlow::WaitUntil(next_disk_poll);
+        }
+
+        // Coming back to the window is when files were most likely changed elsewhere.
+        Event::WindowEvent {
+            event: WindowEvent::Focused(true),
+            ..
+        } => editor.check_disk_changes(),
+
+        Event::WindowEvent {
+            event: WindowEvent::Focused(false),
+            ..
+        } => editor.handle_focus_lost(),
+
+        Event::DeviceEvent {
+            event: DeviceEvent::ModifiersChanged(new_modifiers),
+            ..
+        } => modifiers = new_modifiers,
 
         Event::WindowEvent {
             event: WindowEvent::KeyboardInput { input, .. },
             ..
         } => {
-            if input.virtual_keycode == Some(VirtualKeyCode::LControl) {
-                modifier_pressed = input.state == ElementState::Pressed;
-            }
-            match (input.virtual_keycode, input.modifiers) {
-                // Quit
-                (Some(VirtualKeyCode::Q), ModifiersState::CTRL) => {
-                    *control_flow = ControlFlow::Exit
-                }
-
-                // Save
-                (Some(VirtualKeyCode::S), ModifiersState::CTRL) => {
-                    editor.save();
-                }
+            editor.handle_keyboard_input(input, modifiers);
 
-                _ => {
-                    editor.handle_keyboard_input(input);
-                    // TODO: Only redraw is something has changed
-                    window.request_redraw();
-                }
+            // Answering the unsaved changes prompt may have let us quit.
+            if editor.should_quit() {
+                *control_flow = ControlFlow::Exit;
             }
         }
 
@@ -110,10 +283,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             event: WindowEvent::ReceivedCharacter(input),
             ..
         } => {
-            if !modifier_pressed {
+            if !modifiers.ctrl() {
                 editor.handle_char_input(input);
-                // TODO: Only redraw is something has changed
-                window.request_redraw();
+            }
+
+            if editor.should_quit() {
+                *control_flow = ControlFlow::Exit;
             }
         }
 
@@ -123,7 +298,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         } => {
             cursor_position = position;
             editor.handle_mouse_move(cursor_position);
-            window.request_redraw();
         }
 
         Event::WindowEvent {
@@ -131,7 +305,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ..
         } => {
             editor.handle_mouse_input(button, state, cursor_position);
-            window.request_redraw();
         }
 
         Event::WindowEvent {
@@ -145,7 +318,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             // Fix scroll direction
             // TODO: query user preferences
             editor.scroll(-delta.y as f32);
-            window.request_redraw();
         }
 
         Event::WindowEvent {
@@ -154,80 +326,62 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         } => {
             size = new_size;
             editor.update_size(size);
-
-            swap_chain = device.create_swap_chain(
-                &surface,
-                &wgpu::SwapChainDescriptor {
-                    usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-                    format: render_format,
-                    width: size.width,
-                    height: size.height,
-                    present_mode: wgpu::PresentMode::Vsync,
-                },
-            );
-
-            window.request_redraw();
+            swap_chain = None;
         }
 
         Event::RedrawRequested(_) => {
-            let dt = last_frame.elapsed().as_millis();
-            let fps = 1.0 / ((dt as f32) / 1000.0);
-            last_frame = std::time::Instant::now();
+            let title = editor.window_title();
+            if title != window_title {
+                window.set_title(&title);
+                window_title = title;
+            }
 
+            if swap_chain.is_none() {
+                swap_chain =
+                    create_swap_chain(&device, &surface, size, render_format, present_mode);
+            }
+            // wgpu 0.4 doesn't report failing to get a texture. It reconfigures the swap chain
+            // itself, and a frame that timed out is drawn nowhere.
+            let frame = match &mut swap_chain {
+                Some(swap_chain) => swap_chain.get_next_texture(),
+                None => return,
+            };
             let mut encoder =
                 device.create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
 
-            let frame = swap_chain.get_next_texture();
-
-            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    load_op: wgpu::LoadOp::Clear,
-                    store_op: wgpu::StoreOp::Store,
-                    clear_color: wgpu::Color {
-                        r: 0.03,
-                        g: 0.03,
-                        b: 0.03,
-                        a: 1.0,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            editor.draw(size, &mut glyph_brush, &mut rectangle_brush);
-
-            rectangle_brush.draw(
-                &device,
+            editor.draw(&mut renderer);
+            let rendered = renderer.render(
+                &mut device,
                 &mut encoder,
                 &frame.view,
-                (size.width as f64, size.height as f64),
+                (size.width, size.height),
+                editor.config().colors.background,
             );
-
-            glyph_brush.queue(Section {
-                text: &format!("{:.2} fps", fps),
-                screen_position: (size.width as f32 - 200.0, 5.0),
-                scale: Scale::uniform(40.0),
-                color: [1.0, 1.0, 1.0, 1.0],
-                ..Section::default()
-            });
-
-            glyph_brush
-                .draw_queued(
-                    &mut device,
-                    &mut encoder,
-                    &frame.view,
-                    size.width,
-                    size.height,
-                )
-                .expect("Failed to draw queued text.");
-
             queue.submit(&[encoder.finish()]);
+            drop(frame);
+
+            // The next frame starts over with a new swap chain. The same failure is only reported
+            // once, so one that keeps happening doesn't keep asking for frames.
+            match rendered {
+                Ok(()) => render_error = None,
+                Err(message) => {
+                    swap_chain = None;
+                    if render_error.as_ref() != Some(&message) {
+                        editor.set_status_message(format!("Failed to draw: {}", message));
+                        render_error = Some(message);
+                    }
+                }
+            }
+        }
+
+        // Handling the events noted whether anything on screen changed, so a frame is only
+        // drawn when it would look different.
+        Event::MainEventsCleared => {
+            if editor.take_redraw() {
+                window.request_redraw();
+            }
         }
 
-        // Event::EventsCleared => {
-        //     window.request_redraw();
-        // }
-        _ => *control_flow = ControlFlow::Wait,
+        _ => *control_flow = ControlFlow::WaitUntil(next_disk_poll),
     });
 }
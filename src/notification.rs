@@ -0,0 +1,45 @@
+use crate::layout::Rect;
+use crate::renderer::{Layer, Renderer};
+use wgpu_glyph::{Scale, Section};
+
+pub const NOTIFICATION_HEIGHT: f32 = 40.0;
+const NOTIFICATION_SCALE: f32 = 26.0;
+const NOTIFICATION_PADDING: f32 = 16.0;
+
+/// A message along the bottom of the window, e.g. about a save that failed.
+/// It stays up until the user dismisses it with Escape or a click.
+pub struct Notification {
+    message: String,
+}
+
+impl Notification {
+    pub fn error(message: String) -> Self {
+        Self { message }
+    }
+
+    pub fn draw(&self, bounds: Rect, renderer: &mut Renderer) {
+        renderer.queue_rectangle(
+            Layer::Background,
+            bounds.x as i32,
+            bounds.y as i32,
+            bounds.width as i32,
+            bounds.height as i32,
+            [0.45, 0.08, 0.08, 1.0],
+        );
+
+        renderer.queue_text(
+            Layer::Text,
+            Section {
+                text: &self.message,
+                screen_position: (
+                    bounds.x + NOTIFICATION_PADDING,
+                    bounds.y + (bounds.height - NOTIFICATION_SCALE) / 2.0,
+                ),
+                bounds: (bounds.width - NOTIFICATION_PADDING * 2.0, bounds.height),
+                scale: Scale::uniform(NOTIFICATION_SCALE),
+                color: [1.0, 1.0, 1.0, 1.0],
+                ..Section::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use crate::fuzzy::{fuzzy_match, Match};
+use crate::layout::Rect;
+use crate::rectangle_brush::RectStyle;
+use crate::renderer::{Layer, Renderer};
+use wgpu_glyph::{Scale, Section, SectionText, VariedSection};
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+
+const PALETTE_SCALE: f32 = 28.0;
+const PALETTE_PADDING: f32 = 16.0;
+const ROW_HEIGHT: f32 = PALETTE_SCALE + 8.0;
+const MAX_ROWS: usize = 12;
+const CORNER_RADIUS: f32 = 8.0;
+/// Corner radius of the input and the selected row.
+const INNER_CORNER_RADIUS: f32 = 4.0;
+const BORDER_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+
+const TEXT_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const MATCH_COLOR: [f32; 4] = [0.4, 0.7, 1.0, 1.0];
+const DETAIL_COLOR: [f32; 4] = [0.55, 0.55, 0.55, 1.0];
+
+/// Something to pick from the palette, with a note shown on the right, e.g. its keybinding.
+pub struct PaletteItem {
+    pub label: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteAnswer {
+    /// The index of the item in the list it was given.
+    Pick(usize),
+    Cancel,
+}
+
+/// An overlay along the top of the window with a text input that fuzzy filters a list of items.
+pub struct Palette {
+    placeholder: String,
+    input: String,
+    items: Vec<PaletteItem>,
+    // Indices into `items` of those matching the input, best first, with the matched characters.
+    matches: Vec<(usize, Match)>,
+    selected: usize,
+    // First row of `matches` that is shown.
+    scroll: usize,
+}
+
+impl Palette {
+    pub fn new(placeholder: String, items: Vec<PaletteItem>) -> Self {
+        let mut palette = Self {
+            placeholder,
+            input: String::new(),
+            items,
+            matches: vec![],
+            selected: 0,
+            scroll: 0,
+        };
+        palette.filter();
+        palette
+    }
+
+    /// Matches the items from `start` on against the input and adds them to `matches`.
+    fn match_items(&mut self, start: usize) {
+        let input = &self.input;
+        let new_matches = self.items[start..]
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                fuzzy_match(input, &item.label).map(|m| (start + index, m))
+            });
+        self.matches.extend(new_matches);
+        // Stable, so equally good matches keep the order they were given in.
+        let items = &self.items;
+        self.matches.sort_by_key(|(index, m)| {
+            (
+                std::cmp::Reverse(m.score),
+                items[*index].label.chars().count(),
+            )
+        });
+    }
+
+    fn filter(&mut self) {
+        self.matches.clear();
+        self.match_items(0);
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// Adds items to the list, e.g. as they are found in the background. The selected item stays
+    /// selected.
+    pub fn extend_items(&mut self, items: impl IntoIterator<Item = PaletteItem>) {
+        let selected = self.matches.get(self.selected).map(|(index, _)| *index);
+        let start = self.items.len();
+        self.items.extend(items);
+        self.match_items(start);
+        if let Some(selected) = selected {
+            let row = self
+                .matches
+                .iter()
+                .position(|(index, _)| *index == selected)
+                .unwrap_or(0);
+            self.select(row);
+        }
+    }
+
+    pub fn set_placeholder(&mut self, placeholder: String) {
+        self.placeholder = placeholder;
+    }
+
+    fn select(&mut self, selected: usize) {
+        self.selected = selected.min(self.matches.len().saturating_sub(1));
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + MAX_ROWS {
+            self.scroll = self.selected + 1 - MAX_ROWS;
+        }
+    }
+
+    /// Typed characters edit the filter, Enter picks the selected item.
+    pub fn handle_char_input(&mut self, input: char) -> Option<PaletteAnswer> {
+        if input == '\n' || input == '\r' {
+            return self
+                .matches
+                .get(self.selected)
+                .map(|(index, _)| PaletteAnswer::Pick(*index));
+        }
+        // this is Backspace
+        if input == '\u{8}' {
+            self.input.pop();
+        } else if !input.is_control() {
+            self.input.push(input);
+        } else {
+            return None;
+        }
+        self.filter();
+        None
+    }
+
+    /// Escape cancels, the arrow and page keys move the selection.
+    pub fn handle_keyboard_input(&mut self, input: KeyboardInput) -> Option<PaletteAnswer> {
+        if input.state == ElementState::Released {
+            return None;
+        }
+
+        match input.virtual_keycode? {
+            VirtualKeyCode::Escape => return Some(PaletteAnswer::Cancel),
+            VirtualKeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            VirtualKeyCode::Down => self.select(self.selected + 1),
+            VirtualKeyCode::PageUp => self.select(self.selected.saturating_sub(MAX_ROWS)),
+            VirtualKeyCode::PageDown => self.select(self.selected + MAX_ROWS),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&self, window: Rect, renderer: &mut Renderer) {
+        let width = (window.width * 0.6).max(400.0).min(window.width);
+        let rows = self.matches.len().clamp(1, MAX_ROWS);
+        let height = PALETTE_PADDING * 3.0 + ROW_HEIGHT * (rows + 1) as f32;
+        let bounds = Rect::new(
+            window.x + (window.width - width) / 2.0,
+            window.y + PALETTE_PADDING,
+            width,
+            height,
+        );
+        let x = bounds.x + PALETTE_PADDING;
+        let inner_width = bounds.width - PALETTE_PADDING * 2.0;
+
+        renderer.queue_styled_rectangle(
+            Layer::Popup,
+            bounds,
+            RectStyle {
+                color: [0.12, 0.12, 0.12, 1.0],
+                corner_radius: CORNER_RADIUS,
+                border_width: 1.0,
+                border_color: BORDER_COLOR,
+            },
+        );
+
+        // The input
+        let mut y = bounds.y + PALETTE_PADDING;
+        renderer.queue_styled_rectangle(
+            Layer::Popup,
+            Rect::new(x - 4.0, y - 4.0, inner_width + 8.0, ROW_HEIGHT),
+            RectStyle {
+                color: [0.03, 0.03, 0.03, 1.0],
+                corner_radius: INNER_CORNER_RADIUS,
+                ..RectStyle::default()
+            },
+        );
+        let (text, color) = if self.input.is_empty() {
+            (&self.placeholder, DETAIL_COLOR)
+        } else {
+            (&self.input, [1.0, 1.0, 1.0, 1.0])
+        };
+        renderer.queue_text(
+            Layer::PopupText,
+            Section {
+                text,
+                screen_position: (x, y),
+                bounds: (inner_width, ROW_HEIGHT),
+                scale: Scale::uniform(PALETTE_SCALE),
+                color,
+                ..Section::default()
+            },
+        );
+        let cursor_x = x + renderer.text_width(&self.input, PALETTE_SCALE);
+        renderer.queue_rectangle(
+            Layer::Popup,
+            cursor_x as i32,
+            y as i32,
+            2,
+            PALETTE_SCALE as i32,
+            [1.0, 1.0, 1.0, 1.0],
+        );
+        y += ROW_HEIGHT + PALETTE_PADDING;
+
+        if self.matches.is_empty() {
+            renderer.queue_text(
+                Layer::PopupText,
+                Section {
+                    text: "No matches",
+                    screen_position: (x, y),
+                    bounds: (inner_width, ROW_HEIGHT),
+                    scale: Scale::uniform(PALETTE_SCALE),
+                    color: DETAIL_COLOR,
+                    ..Section::default()
+                },
+            );
+            return;
+        }
+
+        for (row, (index, m)) in self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(MAX_ROWS)
+        {
+            let item = &self.items[*index];
+            if row == self.selected {
+                renderer.queue_styled_rectangle(
+                    Layer::Popup,
+                    Rect::new(x - 4.0, y - 4.0, inner_width + 8.0, ROW_HEIGHT),
+                    RectStyle {
+                        color: [0.15, 0.3, 0.55, 1.0],
+                        corner_radius: INNER_CORNER_RADIUS,
+                        ..RectStyle::default()
+                    },
+                );
+            }
+
+            let detail_width = renderer.text_width(&item.detail, PALETTE_SCALE);
+            renderer.queue_text(
+                Layer::PopupText,
+                Section {
+                    text: &item.detail,
+                    screen_position: (x + inner_width - detail_width, y),
+                    bounds: (detail_width, ROW_HEIGHT),
+                    scale: Scale::uniform(PALETTE_SCALE),
+                    color: DETAIL_COLOR,
+                    ..Section::default()
+                },
+            );
+
+            // Split the label into runs of matched and unmatched characters to color them.
+            let mut runs: Vec<(String, bool)> = vec![];
+            let mut positions = m.positions.iter().peekable();
+            for (char_index, c) in item.label.chars().enumerate() {
+                let matched = positions.peek() == Some(&&char_index);
+                if matched {
+                    positions.next();
+                }
+                match runs.last_mut() {
+                    Some((run, run_matched)) if *run_matched == matched => run.push(c),
+                    _ => runs.push((c.to_string(), matched)),
+                }
+            }
+            renderer.queue_text(
+                Layer::PopupText,
+                VariedSection {
+                    screen_position: (x, y),
+                    bounds: (inner_width - detail_width - PALETTE_PADDING, ROW_HEIGHT),
+                    text: runs
+                        .iter()
+                        .map(|(run, matched)| SectionText {
+                            text: run,
+                            scale: Scale::uniform(PALETTE_SCALE),
+                            color: if *matched { MATCH_COLOR } else { TEXT_COLOR },
+                            ..SectionText::default()
+                        })
+                        .collect(),
+                    ..VariedSection::default()
+                },
+            );
+
+            y += ROW_HEIGHT;
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use crate::layout::Rect;
+use crate::renderer::{Layer, Renderer};
+use crate::session::Layout;
+use crate::view::View;
+use winit::dpi::PhysicalPosition;
+
+const DIVIDER_WIDTH: f32 = 4.0;
+// Keep dragged dividers from squashing a pane down to nothing.
+const MIN_RATIO: f32 = 0.1;
+const MAX_RATIO: f32 = 0.9;
+
+pub type PaneId = usize;
+
+/// How the two halves of a split are arranged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Side by side, left to right.
+    Horizontal,
+    /// Stacked, top to bottom.
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A tile of the window showing one document through its own view.
+pub struct Pane {
+    id: PaneId,
+    pub document: usize,
+    pub view: View,
+}
+
+impl Pane {
+    pub fn bounds(&self) -> Rect {
+        self.view.bounds()
+    }
+}
+
+enum Node {
+    Leaf(PaneId),
+    Split {
+        axis: Axis,
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn find_leaf_mut(&mut self, id: PaneId) -> Option<&mut Node> {
+        match self {
+            Node::Leaf(leaf) if *leaf == id => Some(self),
+            Node::Leaf(_) => None,
+            Node::Split { first, second, .. } => match first.find_leaf_mut(id) {
+                Some(node) => Some(node),
+                None => second.find_leaf_mut(id),
+            },
+        }
+    }
+
+    /// Removes the leaf for `id`, collapsing its parent split into the sibling.
+    /// Returns `None` if the whole node went away.
+    fn remove_leaf(self, id: PaneId) -> Option<Node> {
+        match self {
+            Node::Leaf(leaf) if leaf == id => None,
+            Node::Leaf(_) => Some(self),
+            Node::Split {
+                axis,
+                ratio,
+                first,
+                second,
+            } => match (first.remove_leaf(id), second.remove_leaf(id)) {
+                (Some(first), Some(second)) => Some(Node::Split {
+                    axis,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            },
+        }
+    }
+
+    fn split_at_path_mut(&mut self, path: &[bool]) -> Option<&mut f32> {
+        match self {
+            Node::Leaf(_) => None,
+            Node::Split {
+                ratio,
+                first,
+                second,
+                ..
+            } => match path.split_first() {
+                None => Some(ratio),
+                Some((false, rest)) => first.split_at_path_mut(rest),
+                Some((true, rest)) => second.split_at_path_mut(rest),
+            },
+        }
+    }
+}
+
+struct Divider {
+    rect: Rect,
+    axis: Axis,
+    // Bounds of the split this divider belongs to, to turn mouse positions into ratios.
+    split_bounds: Rect,
+    // Path to the split node from the root, `false` for the first child and `true` for the second.
+    path: Vec<bool>,
+}
+
+fn layout_node(
+    node: &Node,
+    bounds: Rect,
+    path: &mut Vec<bool>,
+    rects: &mut Vec<(PaneId, Rect)>,
+    dividers: &mut Vec<Divider>,
+) {
+    match node {
+        Node::Leaf(id) => rects.push((*id, bounds)),
+        Node::Split {
+            axis,
+            ratio,
+            first,
+            second,
+        } => {
+            let (first_bounds, divider, second_bounds) = match axis {
+                Axis::Horizontal => {
+                    let first_width = ((bounds.width - DIVIDER_WIDTH) * ratio).floor();
+                    let divider_x = bounds.x + first_width;
+                    (
+                        Rect::new(bounds.x, bounds.y, first_width, bounds.height),
+                        Rect::new(divider_x, bounds.y, DIVIDER_WIDTH, bounds.height),
+                        Rect::new(
+                            divider_x + DIVIDER_WIDTH,
+                            bounds.y,
+                            bounds.right() - divider_x - DIVIDER_WIDTH,
+                            bounds.height,
+                        ),
+                    )
+                }
+                Axis::Vertical => {
+                    let first_height = ((bounds.height - DIVIDER_WIDTH) * ratio).floor();
+                    let divider_y = bounds.y + first_height;
+                    (
+                        Rect::new(bounds.x, bounds.y, bounds.width, first_height),
+                        Rect::new(bounds.x, divider_y, bounds.width, DIVIDER_WIDTH),
+                        Rect::new(
+                            bounds.x,
+                            divider_y + DIVIDER_WIDTH,
+                            bounds.width,
+                            bounds.bottom() - divider_y - DIVIDER_WIDTH,
+                        ),
+                    )
+                }
+            };
+
+            dividers.push(Divider {
+                rect: divider,
+                axis: *axis,
+                split_bounds: bounds,
+                path: path.clone(),
+            });
+
+            path.push(false);
+            layout_node(first, first_bounds, path, rects, dividers);
+            path.pop();
+            path.push(true);
+            layout_node(second, second_bounds, path, rects, dividers);
+            path.pop();
+        }
+    }
+}
+
+/// Tiles an area of the window into panes using nested horizontal and vertical splits.
+pub struct PaneTree {
+    root: Node,
+    panes: Vec<Pane>,
+    focused: PaneId,
+    next_id: PaneId,
+    bounds: Rect,
+    dividers: Vec<Divider>,
+    dragging_divider: Option<usize>,
+}
+
+impl PaneTree {
+    pub fn new(bounds: Rect, document: usize) -> Self {
+        let mut tree = Self {
+            root: Node::Leaf(0),
+            panes: vec![Pane {
+                id: 0,
+                document,
+                view: View::new(bounds),
+            }],
+            focused: 0,
+            next_id: 1,
+            bounds,
+            dividers: vec![],
+            dragging_divider: None,
+        };
+        tree.layout();
+        tree
+    }
+
+    /// Rebuilds a saved layout. Documents are taken as they are, so check them beforehand.
+    pub fn from_layout(bounds: Rect, layout: &Layout, focused: usize) -> Self {
+        fn build(layout: &Layout, panes: &mut Vec<Pane>, bounds: Rect) -> Node {
+            match layout {
+                Layout::Pane { document, view } => {
+                    let id = panes.len();
+                    panes.push(Pane {
+                        id,
+                        document: *document,
+                        view: View::from_state(bounds, view),
+                    });
+                    Node::Leaf(id)
+                }
+                Layout::Split {
+                    axis,
+                    ratio,
+                    first,
+                    second,
+                } => Node::Split {
+                    axis: *axis,
+                    ratio: ratio.clamp(MIN_RATIO, MAX_RATIO),
+                    first: Box::new(build(first, panes, bounds)),
+                    second: Box::new(build(second, panes, bounds)),
+                },
+            }
+        }
+
+        let mut panes = vec![];
+        let root = build(layout, &mut panes, bounds);
+        let mut tree = Self {
+            root,
+            focused: focused.min(panes.len() - 1),
+            next_id: panes.len(),
+            panes,
+            bounds,
+            dividers: vec![],
+            dragging_divider: None,
+        };
+        tree.layout();
+        tree
+    }
+
+    /// The layout to save in a session, and the position of the focused pane in it.
+    pub fn to_layout(&self) -> (Layout, usize) {
+        fn save(node: &Node, tree: &PaneTree, order: &mut Vec<PaneId>) -> Layout {
+            match node {
+                Node::Leaf(id) => {
+                    order.push(*id);
+                    let pane = tree.panes.iter().find(|pane| pane.id == *id).unwrap();
+                    Layout::Pane {
+                        document: pane.document,
+                        view: pane.view.state(),
+                    }
+                }
+                Node::Split {
+                    axis,
+                    ratio,
+                    first,
+                    second,
+                } => Layout::Split {
+                    axis: *axis,
+                    ratio: *ratio,
+                    first: Box::new(save(first, tree, order)),
+                    second: Box::new(save(second, tree, order)),
+                },
+            }
+        }
+
+        let mut order = vec![];
+        let layout = save(&self.root, self, &mut order);
+        let focused = order.iter().position(|&id| id == self.focused).unwrap();
+        (layout, focused)
+    }
+
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        self.layout();
+    }
+
+    fn layout(&mut self) {
+        let mut rects = vec![];
+        self.dividers.clear();
+        layout_node(
+            &self.root,
+            self.bounds,
+            &mut vec![],
+            &mut rects,
+            &mut self.dividers,
+        );
+        for (id, rect) in rects {
+            if let Some(pane) = self.panes.iter_mut().find(|pane| pane.id == id) {
+                pane.view.set_bounds(rect);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Pane> {
+        self.panes.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Pane> {
+        self.panes.iter_mut()
+    }
+
+    /// Every pane except the focused one.
+    pub fn unfocused_mut(&mut self) -> impl Iterator<Item = &mut Pane> {
+        let focused = self.focused;
+        self.panes.iter_mut().filter(move |pane| pane.id != focused)
+    }
+
+    pub fn is_focused(&self, pane: &Pane) -> bool {
+        pane.id == self.focused
+    }
+
+    pub fn focused(&self) -> &Pane {
+        let focused = self.focused;
+        self.panes.iter().find(|pane| pane.id == focused).unwrap()
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Pane {
+        let focused = self.focused;
+        self.panes
+            .iter_mut()
+            .find(|pane| pane.id == focused)
+            .unwrap()
+    }
+
+    pub fn pane_at_mut(&mut self, position: PhysicalPosition<i32>) -> Option<&mut Pane> {
+        self.panes
+            .iter_mut()
+            .find(|pane| pane.bounds().contains(position))
+    }
+
+    /// Focuses the pane under `position`, returning `false` if there is none.
+    pub fn focus_at(&mut self, position: PhysicalPosition<i32>) -> bool {
+        match self.pane_at_mut(position).map(|pane| pane.id) {
+            Some(id) => {
+                self.focused = id;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves focus to the closest pane in `direction`, if there is one.
+    pub fn focus_direction(&mut self, direction: Direction) {
+        let from = self.focused().bounds();
+        let center = (from.x + from.width / 2.0, from.y + from.height / 2.0);
+
+        let candidate = self
+            .panes
+            .iter()
+            .filter(|pane| {
+                let to = pane.bounds();
+                match direction {
+                    Direction::Left => to.right() <= from.x,
+                    Direction::Right => to.x >= from.right(),
+                    Direction::Up => to.bottom() <= from.y,
+                    Direction::Down => to.y >= from.bottom(),
+                }
+            })
+            .map(|pane| {
+                let to = pane.bounds();
+                let dx = (to.x + to.width / 2.0) - center.0;
+                let dy = (to.y + to.height / 2.0) - center.1;
+                (pane.id, dx * dx + dy * dy)
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        if let Some((id, _)) = candidate {
+            self.focused = id;
+        }
+    }
+
+    /// Splits the focused pane in two. The new pane shows the same document and takes focus.
+    pub fn split(&mut self, axis: Axis) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let (document, view) = {
+            let focused = self.focused();
+            (focused.document, focused.view.clone())
+        };
+        self.panes.push(Pane { id, document, view });
+
+        let focused = self.focused;
+        if let Some(leaf) = self.root.find_leaf_mut(focused) {
+            *leaf = Node::Split {
+                axis,
+                ratio: 0.5,
+                first: Box::new(Node::Leaf(focused)),
+                second: Box::new(Node::Leaf(id)),
+            };
+        }
+
+        self.focused = id;
+        self.layout();
+    }
+
+    /// Closes the focused pane and hands its space to its sibling.
+    /// The last pane can't be closed.
+    pub fn close_focused(&mut self) {
+        if self.panes.len() == 1 {
+            return;
+        }
+
+        let focused = self.focused;
+        let closed = self.focused().bounds();
+        let root = std::mem::replace(&mut self.root, Node::Leaf(focused));
+        self.root = root.remove_leaf(focused).unwrap();
+        self.panes.retain(|pane| pane.id != focused);
+        self.layout();
+
+        // Hand focus to whichever pane took over the space of the closed one.
+        let center = PhysicalPosition::new(
+            (closed.x + closed.width / 2.0) as i32,
+            (closed.y + closed.height / 2.0) as i32,
+        );
+        if !self.focus_at(center) {
+            self.focused = self.panes[0].id;
+        }
+    }
+
+    pub fn is_dragging_divider(&self) -> bool {
+        self.dragging_divider.is_some()
+    }
+
+    /// Starts dragging the divider under `position`, returning `false` if there is none.
+    pub fn begin_divider_drag(&mut self, position: PhysicalPosition<i32>) -> bool {
+        self.dragging_divider = self
+            .dividers
+            .iter()
+            .position(|divider| divider.rect.contains(position));
+        self.dragging_divider.is_some()
+    }
+
+    pub fn end_divider_drag(&mut self) {
+        self.dragging_divider = None;
+    }
+
+    pub fn drag_divider(&mut self, position: PhysicalPosition<i32>) {
+        let divider = match self.dragging_divider {
+            Some(index) => &self.dividers[index],
+            None => return,
+        };
+
+        let bounds = divider.split_bounds;
+        let new_ratio = match divider.axis {
+            Axis::Horizontal => (position.x as f32 - bounds.x) / bounds.width,
+            Axis::Vertical => (position.y as f32 - bounds.y) / bounds.height,
+        };
+
+        if let Some(ratio) = self.root.split_at_path_mut(&divider.path) {
+            *ratio = new_ratio.clamp(MIN_RATIO, MAX_RATIO);
+        }
+        self.layout();
+    }
+
+    pub fn draw_dividers(&self, renderer: &mut Renderer) {
+        for divider in &self.dividers {
+            renderer.queue_rectangle(
+                Layer::Background,
+                divider.rect.x as i32,
+                divider.rect.y as i32,
+                divider.rect.width as i32,
+                divider.rect.height as i32,
+                [0.15, 0.15, 0.15, 1.0],
+            );
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Writing PNG images, just enough for screenshots.
+//!
+//! The image data is stored without compression, which keeps the encoder small at the cost of
+//! larger files.
+
+use std::{fs, io, path::Path};
+
+/// The most a stored deflate block can hold.
+const MAX_BLOCK_SIZE: usize = 0xffff;
+
+/// Writes `pixels`, given as 8 bit RGBA rows from the top, to a PNG at `path`.
+pub fn write(path: &Path, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+    fs::write(path, encode(width, height, pixels))
+}
+
+fn encode(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width as usize * height as usize * 4);
+
+    // Each row starts with its filter type, 0 for none.
+    let mut raw = Vec::with_capacity(pixels.len() + height as usize);
+    for row in pixels.chunks(width as usize * 4) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, default compression and filtering, not interlaced.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream of uncompressed blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(last as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn be_u32(bytes: &[u8]) -> u32 {
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    /// Splits `png` into its chunks, checking the signature and the CRC of each.
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = vec![];
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = be_u32(rest) as usize;
+            let (kind_and_data, crc) = rest[4..].split_at(4 + len);
+            assert_eq!(crc32(kind_and_data), be_u32(crc));
+            let mut kind = [0; 4];
+            kind.copy_from_slice(&kind_and_data[..4]);
+            chunks.push((kind, &kind_and_data[4..]));
+            rest = &crc[4..];
+        }
+        chunks
+    }
+
+    /// Reads a zlib stream of stored blocks back, checking its Adler-32.
+    fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(&stream[..2], &[0x78, 0x01]);
+        let mut data = vec![];
+        let mut rest = &stream[2..];
+        loop {
+            let last = rest[0] == 1;
+            let len = u16::from_le_bytes([rest[1], rest[2]]);
+            assert_eq!(u16::from_le_bytes([rest[3], rest[4]]), !len);
+            data.extend_from_slice(&rest[5..5 + len as usize]);
+            rest = &rest[5 + len as usize..];
+            if last {
+                break;
+            }
+        }
+        assert_eq!(rest.len(), 4);
+        assert_eq!(adler32(&data), be_u32(rest));
+        data
+    }
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn encodes_a_small_image() {
+        let pixels = [
+            0xff, 0x00, 0x00, 0xff, 0x00, 0xff, 0x00, 0x80, //
+            0x00, 0x00, 0xff, 0xff, 0x12, 0x34, 0x56, 0x78,
+        ];
+        let png = encode(2, 2, &pixels);
+        let chunks = chunks(&png);
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, &[0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        let mut raw = vec![0];
+        raw.extend_from_slice(&pixels[..8]);
+        raw.push(0);
+        raw.extend_from_slice(&pixels[8..]);
+        assert_eq!(inflate_stored(chunks[1].1), raw);
+        // The file ends with an empty IEND chunk.
+        assert_eq!(
+            &png[png.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn splits_large_images_into_blocks() {
+        let (width, height) = (200, 100);
+        let pixels: Vec<u8> = (0..width * height * 4).map(|i| (i * 7) as u8).collect();
+        let png = encode(width, height, &pixels);
+        let chunks = chunks(&png);
+        let raw = inflate_stored(chunks[1].1);
+        assert_eq!(raw.len(), (width * 4 + 1) as usize * height as usize);
+        let rows: Vec<u8> = raw
+            .chunks(width as usize * 4 + 1)
+            .flat_map(|row| {
+                assert_eq!(row[0], 0);
+                row[1..].to_vec()
+            })
+            .collect();
+        assert_eq!(rows, pixels);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::layout::Rect;
+use crate::rectangle_brush::RectStyle;
+use crate::renderer::{Layer, Renderer};
+use wgpu_glyph::{Scale, Section};
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+
+const PROMPT_SCALE: f32 = 28.0;
+const PROMPT_PADDING: f32 = 20.0;
+const LINE_HEIGHT: f32 = PROMPT_SCALE + 6.0;
+const CORNER_RADIUS: f32 = 8.0;
+/// Corner radius of the input and the buttons.
+const INNER_CORNER_RADIUS: f32 = 4.0;
+const BORDER_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+
+/// A button in a prompt, picked by pressing Enter while it is selected or typing its shortcut.
+pub struct Choice {
+    pub label: &'static str,
+    pub shortcut: char,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptAnswer {
+    Choice(usize),
+    Text(String),
+    Cancel,
+}
+
+/// A modal box in the middle of the window asking the user to pick a choice or type some text.
+pub struct Prompt {
+    title: String,
+    lines: Vec<String>,
+    choices: Vec<Choice>,
+    selected: usize,
+    // `Some` for prompts that ask for text rather than a choice.
+    input: Option<String>,
+}
+
+impl Prompt {
+    pub fn choose(title: String, lines: Vec<String>, choices: Vec<Choice>) -> Self {
+        Self {
+            title,
+            lines,
+            choices,
+            selected: 0,
+            input: None,
+        }
+    }
+
+    pub fn text_input(title: String, initial: String) -> Self {
+        Self {
+            title,
+            lines: vec![],
+            choices: vec![],
+            selected: 0,
+            input: Some(initial),
+        }
+    }
+
+    /// Typed characters pick choices by shortcut, edit the input text, or confirm with Enter.
+    pub fn handle_char_input(&mut self, input: char) -> Option<PromptAnswer> {
+        if input == '\n' || input == '\r' {
+            return Some(match self.input.take() {
+                Some(text) => PromptAnswer::Text(text),
+                None => PromptAnswer::Choice(self.selected),
+            });
+        }
+
+        match self.input.as_mut() {
+            Some(text) => {
+                // this is Backspace
+                if input == '\u{8}' {
+                    text.pop();
+                } else if !input.is_control() {
+                    text.push(input);
+                }
+                None
+            }
+            None => {
+                let input = input.to_ascii_lowercase();
+                self.choices
+                    .iter()
+                    .position(|choice| choice.shortcut == input)
+                    .map(PromptAnswer::Choice)
+            }
+        }
+    }
+
+    /// Handles keys that don't produce characters: Escape cancels, arrows and Tab move the
+    /// selected choice.
+    pub fn handle_keyboard_input(&mut self, input: KeyboardInput) -> Option<PromptAnswer> {
+        if input.state == ElementState::Released {
+            return None;
+        }
+
+        let count = self.choices.len();
+        match input.virtual_keycode? {
+            VirtualKeyCode::Escape => return Some(PromptAnswer::Cancel),
+            VirtualKeyCode::Left if count > 0 => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Tab if count > 0 => {
+                self.selected = (self.selected + 1) % count;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&self, window: Rect, renderer: &mut Renderer) {
+        let width = (window.width * 0.6).max(400.0).min(window.width);
+        let rows = 1 + self.lines.len() + 1;
+        let height = PROMPT_PADDING * 2.0 + rows as f32 * LINE_HEIGHT + PROMPT_PADDING;
+        let bounds = Rect::new(
+            window.x + (window.width - width) / 2.0,
+            window.y + (window.height - height) / 3.0,
+            width,
+            height,
+        );
+
+        renderer.queue_styled_rectangle(
+            Layer::Popup,
+            bounds,
+            RectStyle {
+                color: [0.12, 0.12, 0.12, 1.0],
+                corner_radius: CORNER_RADIUS,
+                border_width: 1.0,
+                border_color: BORDER_COLOR,
+            },
+        );
+
+        let x = bounds.x + PROMPT_PADDING;
+        let mut y = bounds.y + PROMPT_PADDING;
+
+        renderer.queue_text(
+            Layer::PopupText,
+            Section {
+                text: &self.title,
+                screen_position: (x, y),
+                bounds: (bounds.width - PROMPT_PADDING * 2.0, LINE_HEIGHT),
+                scale: Scale::uniform(PROMPT_SCALE),
+                color: [1.0, 1.0, 1.0, 1.0],
+                ..Section::default()
+            },
+        );
+        y += LINE_HEIGHT;
+
+        for line in &self.lines {
+            renderer.queue_text(
+                Layer::PopupText,
+                Section {
+                    text: line,
+                    screen_position: (x, y),
+                    bounds: (bounds.width - PROMPT_PADDING * 2.0, LINE_HEIGHT),
+                    scale: Scale::uniform(PROMPT_SCALE),
+                    color: [0.7, 0.7, 0.7, 1.0],
+                    ..Section::default()
+                },
+            );
+            y += LINE_HEIGHT;
+        }
+
+        y += PROMPT_PADDING;
+
+        if let Some(text) = &self.input {
+            renderer.queue_styled_rectangle(
+                Layer::Popup,
+                Rect::new(
+                    x - 4.0,
+                    y - 4.0,
+                    bounds.width - PROMPT_PADDING * 2.0 + 8.0,
+                    LINE_HEIGHT + 8.0,
+                ),
+                RectStyle {
+                    color: [0.03, 0.03, 0.03, 1.0],
+                    corner_radius: INNER_CORNER_RADIUS,
+                    ..RectStyle::default()
+                },
+            );
+            renderer.queue_text(
+                Layer::PopupText,
+                Section {
+                    text,
+                    screen_position: (x, y),
+                    scale: Scale::uniform(PROMPT_SCALE),
+                    color: [1.0, 1.0, 1.0, 1.0],
+                    ..Section::default()
+                },
+            );
+            let cursor_x = x + renderer.text_width(text, PROMPT_SCALE);
+            renderer.queue_rectangle(
+                Layer::Popup,
+                cursor_x as i32,
+                y as i32,
+                2,
+                PROMPT_SCALE as i32,
+                [1.0, 1.0, 1.0, 1.0],
+            );
+            return;
+        }
+
+        let mut choice_x = x;
+        for (index, choice) in self.choices.iter().enumerate() {
+            let width = renderer.text_width(choice.label, PROMPT_SCALE) + PROMPT_PADDING;
+            let background = if index == self.selected {
+                [0.15, 0.45, 0.85, 1.0]
+            } else {
+                [0.2, 0.2, 0.2, 1.0]
+            };
+            renderer.queue_styled_rectangle(
+                Layer::Popup,
+                Rect::new(choice_x, y - 4.0, width, LINE_HEIGHT + 8.0),
+                RectStyle {
+                    color: background,
+                    corner_radius: INNER_CORNER_RADIUS,
+                    ..RectStyle::default()
+                },
+            );
+            renderer.queue_text(
+                Layer::PopupText,
+                Section {
+                    text: choice.label,
+                    screen_position: (choice_x + PROMPT_PADDING / 2.0, y),
+                    scale: Scale::uniform(PROMPT_SCALE),
+                    color: [1.0, 1.0, 1.0, 1.0],
+                    ..Section::default()
+                },
+            );
+            choice_x += width + PROMPT_PADDING / 2.0;
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::dirs;
+use crate::document::write_atomically;
+use std::{
+    fs::{self, File, TryLockError},
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Marks the first line of a journal, in case the format ever changes.
+const HEADER: &str = "brewcode recovery 2";
+// Starts the names of journals of untitled documents. Those of files start with their escaped
+// absolute path, so the two can't clash.
+const UNTITLED_PREFIX: &str = "untitled-";
+// Ends the names of the files running instances keep locked, named after their owner id.
+const LOCK_SUFFIX: &str = ".lock";
+
+/// What a journal holds the unsaved text of.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Journal {
+    File(PathBuf),
+    /// An untitled document, known by the id from `new_untitled_id`.
+    Untitled(String),
+}
+
+fn recovery_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("recovery"))
+}
+
+/// Where `journal` is written: named after the absolute path of its file, or after its id.
+fn journal_path(journal: &Journal) -> Option<PathBuf> {
+    let name = match journal {
+        Journal::File(path) => dirs::escaped_file_name(path)?,
+        Journal::Untitled(id) => format!("{}{}", UNTITLED_PREFIX, id),
+    };
+    Some(recovery_dir()?.join(name))
+}
+
+fn lock_path(owner: &str) -> Option<PathBuf> {
+    Some(recovery_dir()?.join(format!("{}{}", owner, LOCK_SUFFIX)))
+}
+
+fn millis_since_epoch() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// An id for the journal of an untitled document that no other session will also pick.
+pub fn new_untitled_id() -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{}-{}", millis_since_epoch(), process::id(), count)
+}
+
+// The owner id of this instance and the lock file held under it, taken with the first journal.
+static OWNER: OnceLock<(String, Option<File>)> = OnceLock::new();
+
+/// The id this instance writes into its journals. The lock file named after it stays locked for
+/// as long as the instance runs, so others can tell its journals are still in use. Empty if the
+/// lock couldn't be taken, which leaves the journals to whoever finds them.
+fn owner() -> &'static str {
+    let (owner, _) = OWNER.get_or_init(|| {
+        let owner = format!("{}-{}", millis_since_epoch(), process::id());
+        let lock = lock_path(&owner).and_then(|path| {
+            fs::create_dir_all(path.parent()?).ok()?;
+            let file = File::create(path).ok()?;
+            file.try_lock().ok()?;
+            Some(file)
+        });
+        match lock {
+            Some(lock) => (owner, Some(lock)),
+            None => (String::new(), None),
+        }
+    });
+    owner
+}
+
+/// Whether another instance that is still running wrote journals as `owner`.
+fn is_running_elsewhere(owner: &str) -> bool {
+    let own = OWNER.get().map(|(own, _)| own.as_str());
+    if owner.is_empty() || Some(owner) == own {
+        return false;
+    }
+    let path = match lock_path(owner) {
+        Some(path) => path,
+        None => return false,
+    };
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    match file.try_lock() {
+        Err(TryLockError::WouldBlock) => true,
+        // The instance is gone, so its lock file is too.
+        Ok(()) => {
+            let _ = fs::remove_file(&path);
+            false
+        }
+        Err(TryLockError::Error(_)) => false,
+    }
+}
+
+/// Journals the unsaved `lines` of `journal`, replacing any earlier ones.
+pub fn write(journal: &Journal, lines: &[String]) -> io::Result<()> {
+    write_as(owner(), journal, lines)
+}
+
+fn write_as(owner: &str, journal: &Journal, lines: &[String]) -> io::Result<()> {
+    let journal_path = journal_path(journal)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no recovery directory"))?;
+    fs::create_dir_all(journal_path.parent().unwrap())?;
+    let source = match journal {
+        Journal::File(path) => path.to_string_lossy().into_owned(),
+        Journal::Untitled(_) => "untitled".to_owned(),
+    };
+    let contents = format!("{}\n{}\n{}\n{}", HEADER, owner, source, lines.join("\n"));
+    write_atomically(&journal_path, contents.as_bytes())
+}
+
+/// The lines journaled for `path` by an earlier session that never saved them. Journals another
+/// running instance is still writing are left to it.
+pub fn read(path: &Path) -> Option<Vec<String>> {
+    read_journal(&journal_path(&Journal::File(path.to_owned()))?)
+}
+
+/// The ids and lines of untitled documents that an earlier session never saved, oldest first.
+/// Journals another running instance is still writing are left to it.
+pub fn read_untitled() -> Vec<(String, Vec<String>)> {
+    let entries = match recovery_dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return vec![],
+    };
+    let mut untitled: Vec<(String, Vec<String>)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let id = name.strip_prefix(UNTITLED_PREFIX)?.to_owned();
+            Some((id, read_journal(&entry.path())?))
+        })
+        .collect();
+    // Ids start with the time they were made at.
+    untitled.sort_by_key(|(id, _)| {
+        let time = id
+            .split('-')
+            .next()
+            .and_then(|time| time.parse::<u128>().ok());
+        (time, id.clone())
+    });
+    untitled
+}
+
+fn read_journal(journal_path: &Path) -> Option<Vec<String>> {
+    let contents = fs::read_to_string(journal_path).ok()?;
+    let mut parts = contents.splitn(4, '\n');
+    if parts.next()? != HEADER || is_running_elsewhere(parts.next()?) {
+        return None;
+    }
+    // The path is only there for people looking through the directory.
+    parts.next()?;
+    Some(
+        parts
+            .next()?
+            .split('\n')
+            .map(|line| line.to_owned())
+            .collect(),
+    )
+}
+
+/// Deletes the lock file of this instance, once it quits and has removed its journals.
+pub fn release_owner() {
+    if let Some((owner, Some(_))) = OWNER.get() {
+        if let Some(path) = lock_path(owner) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Deletes `journal`, e.g. once its changes were saved or thrown away.
+pub fn remove(journal: &Journal) {
+    if let Some(journal_path) = journal_path(journal) {
+        // A journal that was never written is fine.
+        let _ = fs::remove_file(journal_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journals_of_running_instances_are_left_alone() {
+        dirs::use_test_data_dir();
+        fs::create_dir_all(recovery_dir().unwrap()).unwrap();
+        let other = format!("other-{}", new_untitled_id());
+        let lock = File::create(lock_path(&other).unwrap()).unwrap();
+        lock.try_lock().unwrap();
+
+        let id = new_untitled_id();
+        let journal = Journal::Untitled(id.clone());
+        let lines = vec!["unsaved".to_owned()];
+        write_as(&other, &journal, &lines).unwrap();
+        let found = |id: &str| read_untitled().into_iter().any(|(found, _)| found == id);
+        assert!(!found(&id));
+
+        // Once the other instance is gone its journals are up for recovery.
+        drop(lock);
+        assert!(found(&id));
+        assert!(!lock_path(&other).unwrap().exists());
+        remove(&journal);
+    }
+
+    #[test]
+    fn own_journals_can_be_read_back() {
+        dirs::use_test_data_dir();
+        let path = std::env::temp_dir().join(format!("brewcode-journal-{}", new_untitled_id()));
+        let journal = Journal::File(path.clone());
+        let lines = vec!["a".to_owned(), "".to_owned()];
+        write(&journal, &lines).unwrap();
+        assert_eq!(read(&path), Some(lines));
+        remove(&journal);
+        assert_eq!(read(&path), None);
+    }
+}
@@ -1,10 +1,72 @@
+use crate::layout::Rect;
+use std::ops::Range;
+
 const DEFAULT_MAX_RECTS: usize = 100;
 
+/// How a rectangle is filled and outlined.
 #[derive(Debug, Clone, Copy)]
-struct RectInstance {
+pub struct RectStyle {
+    pub color: [f32; 4],
+    /// Radius of the rounded corners, 0 for square ones.
+    pub corner_radius: f32,
+    /// Width of the border drawn inside the edges, 0 for none.
+    pub border_width: f32,
+    pub border_color: [f32; 4],
+}
+
+impl RectStyle {
+    /// A plain fill with square corners.
+    pub fn solid(color: [f32; 4]) -> Self {
+        Self {
+            color,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for RectStyle {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 0.0, 0.0, 1.0],
+            corner_radius: 0.0,
+            border_width: 0.0,
+            border_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+// Fields are only read by the GPU through the instance buffer.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct RectInstance {
     left_top: [f32; 2],
     right_bottom: [f32; 2],
     color: [f32; 4],
+    border_color: [f32; 4],
+    clip_left_top: [f32; 2],
+    clip_right_bottom: [f32; 2],
+    corner_radius: f32,
+    border_width: f32,
+}
+
+impl RectInstance {
+    /// A rectangle covering `rect`, with only the part inside `clip` drawn.
+    pub fn new(rect: Rect, style: RectStyle, clip: Option<Rect>) -> Self {
+        let (clip_left_top, clip_right_bottom) = match clip {
+            Some(clip) => ([clip.x, clip.y], [clip.right(), clip.bottom()]),
+            None => ([f32::MIN, f32::MIN], [f32::MAX, f32::MAX]),
+        };
+        Self {
+            left_top: [rect.x, rect.y],
+            right_bottom: [rect.right(), rect.bottom()],
+            color: style.color,
+            border_color: style.border_color,
+            clip_left_top,
+            clip_right_bottom,
+            corner_radius: style.corner_radius,
+            border_width: style.border_width,
+        }
+    }
 }
 
 pub struct RectangleBrush {
@@ -12,7 +74,6 @@ pub struct RectangleBrush {
     bind_group: wgpu::BindGroup,
     instance_buffer: wgpu::Buffer,
     transform_buffer: wgpu::Buffer,
-    instance_queue: Vec<RectInstance>,
     rect_capacity: usize,
     current_transform: [f32; 16],
 }
@@ -25,8 +86,8 @@ const IDENTITY_MATRIX: [f32; 16] = [
     0.0, 0.0, 0.0, 1.0,
 ];
 
-fn orthographic_projection(width: f64, height: f64) -> [f32; 16] {
-    #[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
+pub fn orthographic_projection(width: f64, height: f64) -> [f32; 16] {
     [
         2.0 / width as f32, 0.0, 0.0, 0.0,
         0.0, 2.0 / height as f32, 0.0, 0.0,
@@ -37,6 +98,7 @@ fn orthographic_projection(width: f64, height: f64) -> [f32; 16] {
 
 impl RectangleBrush {
     pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        // Compiled from the GLSL next to them, e.g. `glslc rectangle.vert -o rectangle.vert.spv`.
         let vs_bytes = include_bytes!("shaders/rectangle.vert.spv");
         let vs_module = device
             .create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(&vs_bytes[..])).unwrap());
@@ -133,6 +195,36 @@ impl RectangleBrush {
                         format: wgpu::VertexFormat::Float4,
                         offset: 4 * 4,
                     },
+                    // border color
+                    wgpu::VertexAttributeDescriptor {
+                        shader_location: 3,
+                        format: wgpu::VertexFormat::Float4,
+                        offset: 4 * 8,
+                    },
+                    // clip top-left
+                    wgpu::VertexAttributeDescriptor {
+                        shader_location: 4,
+                        format: wgpu::VertexFormat::Float2,
+                        offset: 4 * 12,
+                    },
+                    // clip bottom-right
+                    wgpu::VertexAttributeDescriptor {
+                        shader_location: 5,
+                        format: wgpu::VertexFormat::Float2,
+                        offset: 4 * 14,
+                    },
+                    // corner radius
+                    wgpu::VertexAttributeDescriptor {
+                        shader_location: 6,
+                        format: wgpu::VertexFormat::Float,
+                        offset: 4 * 16,
+                    },
+                    // border width
+                    wgpu::VertexAttributeDescriptor {
+                        shader_location: 7,
+                        format: wgpu::VertexFormat::Float,
+                        offset: 4 * 17,
+                    },
                 ],
             }],
             sample_count: 1,
@@ -146,37 +238,28 @@ impl RectangleBrush {
             transform_buffer,
             instance_buffer,
             current_transform: IDENTITY_MATRIX,
-            instance_queue: vec![],
             rect_capacity: DEFAULT_MAX_RECTS,
         }
     }
 
-    pub fn queue_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32, color: [f32; 4]) {
-        let instance = RectInstance {
-            left_top: [x as f32, y as f32],
-            right_bottom: [(x + width) as f32, (y + height) as f32],
-            color,
-        };
-        self.instance_queue.push(instance);
-    }
-
-    pub fn draw(
+    /// Uploads `instances` and the projection for a target of `size`, to be drawn with `draw`.
+    pub fn prepare(
         &mut self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
+        instances: &[RectInstance],
         size: (f64, f64),
     ) {
-        if self.instance_queue.is_empty() {
+        if instances.is_empty() {
             return;
         }
 
-        let instance_count = self.instance_queue.len();
+        let instance_count = instances.len();
 
         // If we have more rectangles than the instance buffer can fit, resize instance buffer.
         if instance_count > self.rect_capacity {
             self.instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-                size: (std::mem::size_of::<RectInstance>() * instance_count) as u64,
+                size: std::mem::size_of_val(instances) as u64,
                 usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
             });
 
@@ -185,15 +268,14 @@ impl RectangleBrush {
 
         let temp_buffer = device
             .create_buffer_mapped(instance_count, wgpu::BufferUsage::COPY_SRC)
-            .fill_from_slice(self.instance_queue.as_slice());
-        self.instance_queue.clear();
+            .fill_from_slice(instances);
 
         encoder.copy_buffer_to_buffer(
             &temp_buffer,
             0,
             &self.instance_buffer,
             0,
-            (std::mem::size_of::<RectInstance>() * instance_count) as u64,
+            std::mem::size_of_val(instances) as u64,
         );
 
         let ortho_proj = orthographic_projection(size.0, size.1);
@@ -206,29 +288,16 @@ impl RectangleBrush {
 
             self.current_transform = ortho_proj;
         }
+    }
 
-        // We don't really need new render passes for this. Let's maybe pass this in the draw call?
-        // This was modelled after wgpu_glyph which wants its own render pass, but there really
-        // isn't a need to do this as we can just use the same render pass and swap out the pipeline.
-        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                attachment: view,
-                resolve_target: None,
-                load_op: wgpu::LoadOp::Load,
-                store_op: wgpu::StoreOp::Store,
-                clear_color: wgpu::Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 0.0,
-                },
-            }],
-            depth_stencil_attachment: None,
-        });
-
+    /// Draws the prepared instances in `range` into the caller's render pass.
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
         rpass.set_pipeline(&self.pipeline);
         rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.set_vertex_buffers(0, &[(&self.instance_buffer, 0)]);
-        rpass.draw(0..4, 0..instance_count as u32);
+        rpass.draw(0..4, range);
     }
 }
@@ -0,0 +1,282 @@
+use crate::gpu;
+use crate::layout::{self, Rect};
+use crate::rectangle_brush::{orthographic_projection, RectInstance, RectStyle, RectangleBrush};
+use std::{borrow::Cow, ops::Range};
+use wgpu_glyph::{Font, GlyphBrush, OwnedSectionText, OwnedVariedSection, Region, VariedSection};
+
+/// Where something is drawn in the stack of a frame. Everything in a layer covers the layers
+/// before it, and within a layer text covers rectangles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    /// Panel backgrounds, gutters and the active line.
+    Background,
+    /// The text of the buffers and panels.
+    Text,
+    /// Selections and cursors, drawn over the text they mark.
+    Overlay,
+    /// Prompts, the palette and other panels floating over everything else.
+    Popup,
+    /// The text on popups.
+    PopupText,
+}
+
+const LAYER_COUNT: usize = 5;
+
+impl Layer {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// What was queued in a layer while the same clip was in effect.
+struct Batch {
+    clip: Option<Rect>,
+    rects: Vec<RectInstance>,
+    sections: Vec<OwnedVariedSection>,
+}
+
+/// Collects the rectangles and text of a frame by layer, and draws them bottom to top.
+pub struct Renderer {
+    rect_brush: RectangleBrush,
+    glyph_brush: GlyphBrush<'static, ()>,
+    layers: [Vec<Batch>; LAYER_COUNT],
+    clips: Vec<Rect>,
+    /// Whether the target encodes to sRGB itself, so colours, which are given in sRGB, have to
+    /// be converted to linear first.
+    srgb_target: bool,
+}
+
+impl Renderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        glyph_brush: GlyphBrush<'static, ()>,
+    ) -> Self {
+        Self {
+            rect_brush: RectangleBrush::new(device, format),
+            glyph_brush,
+            layers: Default::default(),
+            clips: vec![],
+            srgb_target: gpu::is_srgb(format),
+        }
+    }
+
+    /// Swaps in a brush built with another font.
+    pub fn set_glyph_brush(&mut self, glyph_brush: GlyphBrush<'static, ()>) {
+        self.glyph_brush = glyph_brush;
+    }
+
+    pub fn font(&self) -> &Font<'_> {
+        self.glyph_brush.fonts().first().unwrap()
+    }
+
+    /// Horizontal advance of `text` at `scale`.
+    pub fn text_width(&self, text: &str, scale: f32) -> f32 {
+        layout::text_width(&self.glyph_brush, text, scale)
+    }
+
+    /// Clips everything queued from now on to `rect`, within the clip already in effect, until
+    /// the matching `pop_clip`.
+    pub fn push_clip(&mut self, rect: Rect) {
+        let clip = match self.clips.last() {
+            Some(outer) => rect.intersection(*outer),
+            None => rect,
+        };
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    pub fn queue_rectangle(
+        &mut self,
+        layer: Layer,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        color: [f32; 4],
+    ) {
+        let rect = Rect::new(x as f32, y as f32, width as f32, height as f32);
+        self.queue_styled_rectangle(layer, rect, RectStyle::solid(color));
+    }
+
+    /// Queues a rectangle with rounded corners or a border.
+    pub fn queue_styled_rectangle(&mut self, layer: Layer, rect: Rect, mut style: RectStyle) {
+        style.color = self.target_color(style.color);
+        style.border_color = self.target_color(style.border_color);
+        let batch = self.batch(layer);
+        let instance = RectInstance::new(rect, style, batch.clip);
+        batch.rects.push(instance);
+    }
+
+    pub fn queue_text<'a, S>(&mut self, layer: Layer, section: S)
+    where
+        S: Into<Cow<'a, VariedSection<'a>>>,
+    {
+        let section = section.into();
+        let text = section
+            .text
+            .iter()
+            .map(|text| OwnedSectionText {
+                color: self.target_color(text.color),
+                ..OwnedSectionText::from(text)
+            })
+            .collect();
+        self.batch(layer).sections.push(OwnedVariedSection {
+            screen_position: section.screen_position,
+            bounds: section.bounds,
+            z: section.z,
+            layout: section.layout,
+            text,
+        });
+    }
+
+    /// `color` as it has to be written for the target to show it.
+    fn target_color(&self, color: [f32; 4]) -> [f32; 4] {
+        if !self.srgb_target {
+            return color;
+        }
+        let [r, g, b, a] = color;
+        [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+    }
+
+    /// The batch of `layer` for the clip in effect.
+    fn batch(&mut self, layer: Layer) -> &mut Batch {
+        let clip = self.clips.last().copied();
+        let batches = &mut self.layers[layer.index()];
+        if batches.last().map(|batch| batch.clip) != Some(clip) {
+            batches.push(Batch {
+                clip,
+                rects: vec![],
+                sections: vec![],
+            });
+        }
+        batches.last_mut().unwrap()
+    }
+
+    /// Clears `target` to `clear_color` and draws everything queued since the last call into it.
+    ///
+    /// The rectangles of all layers are uploaded together, and consecutive layers without text
+    /// share a render pass. wgpu_glyph opens a pass of its own whenever it draws, so each layer
+    /// with text ends the pass its rectangles were drawn in, and its text is drawn once per clip.
+    pub fn render(
+        &mut self,
+        device: &mut wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        size: (u32, u32),
+        clear_color: [f32; 4],
+    ) -> Result<(), String> {
+        let mut instances = vec![];
+        // The instances of each batch, by layer.
+        let mut ranges: Vec<Vec<(Range<u32>, Option<Rect>)>> = vec![];
+        for batches in &mut self.layers {
+            let mut layer_ranges = vec![];
+            for batch in batches.iter_mut() {
+                let start = instances.len() as u32;
+                instances.append(&mut batch.rects);
+                layer_ranges.push((start..instances.len() as u32, batch.clip));
+            }
+            ranges.push(layer_ranges);
+        }
+        self.rect_brush
+            .prepare(device, encoder, &instances, (size.0 as f64, size.1 as f64));
+
+        let [r, g, b, a] = self.target_color(clear_color);
+        let mut load_op = wgpu::LoadOp::Clear;
+        let mut pending = vec![];
+        for (index, layer_ranges) in ranges.into_iter().enumerate() {
+            pending.extend(layer_ranges);
+            let batches = std::mem::take(&mut self.layers[index]);
+            let has_text = batches.iter().any(|batch| !batch.sections.is_empty());
+            if !has_text && index < LAYER_COUNT - 1 {
+                continue;
+            }
+
+            if load_op == wgpu::LoadOp::Clear || pending.iter().any(|(range, _)| !range.is_empty())
+            {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: target,
+                        resolve_target: None,
+                        load_op,
+                        store_op: wgpu::StoreOp::Store,
+                        clear_color: wgpu::Color {
+                            r: r as f64,
+                            g: g as f64,
+                            b: b as f64,
+                            a: a as f64,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                for (range, clip) in pending.drain(..) {
+                    if let Some(region) = scissor_region(clip, size) {
+                        pass.set_scissor_rect(region.x, region.y, region.width, region.height);
+                        self.rect_brush.draw(&mut pass, range);
+                    }
+                }
+                load_op = wgpu::LoadOp::Load;
+            }
+            pending.clear();
+
+            for batch in batches {
+                if batch.sections.is_empty() {
+                    continue;
+                }
+                // Text clipped away entirely is never queued, as the brush can't unqueue it.
+                let region = match scissor_region(batch.clip, size) {
+                    Some(region) => region,
+                    None => continue,
+                };
+                for section in &batch.sections {
+                    self.glyph_brush.queue(section);
+                }
+                let drawn = self.glyph_brush.draw_queued_with_transform_and_scissoring(
+                    device,
+                    encoder,
+                    target,
+                    orthographic_projection(size.0 as f64, size.1 as f64),
+                    region,
+                );
+                // What the failed frame left queued mustn't end up in the next one.
+                if drawn.is_err() {
+                    self.layers = Default::default();
+                    self.clips.clear();
+                    return drawn;
+                }
+            }
+        }
+        self.clips.clear();
+        Ok(())
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The pixels of a target of `size` inside `clip`, or `None` if there are none.
+fn scissor_region(clip: Option<Rect>, size: (u32, u32)) -> Option<Region> {
+    let target = Rect::new(0.0, 0.0, size.0 as f32, size.1 as f32);
+    let clip = clip.map_or(target, |clip| clip.intersection(target));
+    let x = clip.x.floor() as u32;
+    let y = clip.y.floor() as u32;
+    let width = (clip.right().ceil() as u32).saturating_sub(x);
+    let height = (clip.bottom().ceil() as u32).saturating_sub(y);
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some(Region {
+        x,
+        y,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,225 @@
+use crate::dirs;
+use crate::document::{write_atomically, Location};
+use crate::pane::Axis;
+use serde_json::{json, Value};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where a view was: enough to put the cursor, selection and scroll position back.
+#[derive(Debug, Clone)]
+pub struct ViewState {
+    pub cursor: Location,
+    pub selection_start: Option<Location>,
+    pub scroll: f32,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            cursor: Location::new(),
+            selection_start: None,
+            scroll: 0.0,
+        }
+    }
+}
+
+/// The pane layout, mirroring the split tree of `PaneTree`.
+#[derive(Debug, Clone)]
+pub enum Layout {
+    Pane {
+        /// Index into `Session::documents`.
+        document: usize,
+        view: ViewState,
+    },
+    Split {
+        axis: Axis,
+        ratio: f32,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    /// Every pane in the layout, in the order `PaneTree` numbers them.
+    pub fn panes_mut(&mut self) -> Vec<(&mut usize, &mut ViewState)> {
+        match self {
+            Layout::Pane { document, view } => vec![(document, view)],
+            Layout::Split { first, second, .. } => {
+                let mut panes = first.panes_mut();
+                panes.extend(second.panes_mut());
+                panes
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionDocument {
+    pub path: PathBuf,
+    /// The view the document had when it was last shown, if it isn't in a pane.
+    pub stashed_view: Option<ViewState>,
+}
+
+/// What was open when the editor last exited in a directory.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub documents: Vec<SessionDocument>,
+    pub layout: Layout,
+    /// Position of the focused pane in `Layout::panes_mut` order.
+    pub focused: usize,
+}
+
+fn location_to_json(location: Location) -> Value {
+    json!([location.row, location.col])
+}
+
+fn location_from_json(value: &Value) -> Option<Location> {
+    Some(Location {
+        row: value.get(0)?.as_u64()? as usize,
+        col: value.get(1)?.as_u64()? as usize,
+    })
+}
+
+fn view_to_json(view: &ViewState) -> Value {
+    json!({
+        "cursor": location_to_json(view.cursor),
+        "selection_start": view.selection_start.map(location_to_json),
+        "scroll": view.scroll,
+    })
+}
+
+fn view_from_json(value: &Value) -> Option<ViewState> {
+    Some(ViewState {
+        cursor: location_from_json(value.get("cursor")?)?,
+        selection_start: value.get("selection_start").and_then(location_from_json),
+        scroll: value.get("scroll")?.as_f64()? as f32,
+    })
+}
+
+fn layout_to_json(layout: &Layout) -> Value {
+    match layout {
+        Layout::Pane { document, view } => json!({
+            "document": document,
+            "view": view_to_json(view),
+        }),
+        Layout::Split {
+            axis,
+            ratio,
+            first,
+            second,
+        } => json!({
+            "axis": match axis {
+                Axis::Horizontal => "horizontal",
+                Axis::Vertical => "vertical",
+            },
+            "ratio": ratio,
+            "first": layout_to_json(first),
+            "second": layout_to_json(second),
+        }),
+    }
+}
+
+fn layout_from_json(value: &Value) -> Option<Layout> {
+    if let Some(document) = value.get("document") {
+        return Some(Layout::Pane {
+            document: document.as_u64()? as usize,
+            view: view_from_json(value.get("view")?)?,
+        });
+    }
+
+    let axis = match value.get("axis")?.as_str()? {
+        "horizontal" => Axis::Horizontal,
+        "vertical" => Axis::Vertical,
+        _ => return None,
+    };
+    Some(Layout::Split {
+        axis,
+        ratio: value.get("ratio")?.as_f64()? as f32,
+        first: Box::new(layout_from_json(value.get("first")?)?),
+        second: Box::new(layout_from_json(value.get("second")?)?),
+    })
+}
+
+impl Session {
+    fn to_json(&self) -> Value {
+        let documents: Vec<Value> = self
+            .documents
+            .iter()
+            .map(|document| {
+                json!({
+                    "path": document.path.to_string_lossy(),
+                    "stashed_view": document.stashed_view.as_ref().map(view_to_json),
+                })
+            })
+            .collect();
+        json!({
+            "documents": documents,
+            "layout": layout_to_json(&self.layout),
+            "focused": self.focused,
+        })
+    }
+
+    /// Reads a session back, or `None` if it is malformed. Pane documents that point past the
+    /// document list are rejected too.
+    fn from_json(value: &Value) -> Option<Self> {
+        let documents = value
+            .get("documents")?
+            .as_array()?
+            .iter()
+            .map(|document| {
+                Some(SessionDocument {
+                    path: PathBuf::from(document.get("path")?.as_str()?),
+                    stashed_view: document.get("stashed_view").and_then(view_from_json),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut layout = layout_from_json(value.get("layout")?)?;
+        if layout
+            .panes_mut()
+            .iter()
+            .any(|(document, _)| **document >= documents.len())
+        {
+            return None;
+        }
+        Some(Self {
+            documents,
+            layout,
+            focused: value.get("focused")?.as_u64()? as usize,
+        })
+    }
+}
+
+/// Sessions are kept per project, named after the directory the editor was started in.
+fn session_path() -> Option<PathBuf> {
+    let project = env::current_dir().ok()?;
+    let name = format!("{}.json", dirs::escaped_file_name(&project)?);
+    Some(dirs::data_dir()?.join("sessions").join(name))
+}
+
+/// The session last saved for the current directory.
+pub fn load() -> Option<Session> {
+    let contents = fs::read_to_string(session_path()?).ok()?;
+    Session::from_json(&serde_json::from_str(&contents).ok()?)
+}
+
+pub fn save(session: &Session) -> io::Result<()> {
+    let path = session_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no session directory"))?;
+    fs::create_dir_all(path.parent().unwrap_or_else(|| Path::new(".")))?;
+    let contents = serde_json::to_string_pretty(&session.to_json())?;
+    write_atomically(&path, contents.as_bytes())
+}
+
+/// Forgets the session of the current directory, e.g. when nothing in it could be restored.
+pub fn remove() -> io::Result<()> {
+    let path = match session_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    match fs::remove_file(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
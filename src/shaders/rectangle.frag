@@ -1,9 +1,37 @@
 #version 450
 
 layout(location = 0) in vec4 inColor;
+layout(location = 1) flat in vec4 inBorderColor;
+layout(location = 2) in vec2 inPosition;
+layout(location = 3) flat in vec2 inCenter;
+layout(location = 4) flat in vec2 inHalfSize;
+layout(location = 5) flat in vec4 inClip;
+layout(location = 6) flat in float inCornerRadius;
+layout(location = 7) flat in float inBorderWidth;
 
 layout(location = 0) out vec4 outColor;
 
+// Distance from `p` to the edge of a box with rounded corners centred on the origin, negative
+// inside the box.
+float rounded_box(vec2 p, vec2 half_size, float radius) {
+    vec2 q = abs(p) - half_size + vec2(radius);
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - radius;
+}
+
 void main() {
-    outColor = inColor;
+    if (inPosition.x < inClip.x || inPosition.y < inClip.y ||
+        inPosition.x >= inClip.z || inPosition.y >= inClip.w) {
+        discard;
+    }
+
+    float distance = rounded_box(inPosition - inCenter, inHalfSize, inCornerRadius);
+    // Blend over a pixel at the edges so curves aren't jagged.
+    float coverage = clamp(0.5 - distance, 0.0, 1.0);
+
+    vec4 color = inColor;
+    if (inBorderWidth > 0.0) {
+        float fill = clamp(0.5 - (distance + inBorderWidth), 0.0, 1.0);
+        color = mix(inBorderColor, inColor, fill);
+    }
+    outColor = vec4(color.rgb, color.a * coverage);
 }
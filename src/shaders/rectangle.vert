@@ -7,8 +7,20 @@ layout(set = 0, binding = 0) uniform Transform {
 layout(location = 0) in vec2 left_top;
 layout(location = 1) in vec2 right_bottom;
 layout(location = 2) in vec4 color;
+layout(location = 3) in vec4 border_color;
+layout(location = 4) in vec2 clip_left_top;
+layout(location = 5) in vec2 clip_right_bottom;
+layout(location = 6) in float corner_radius;
+layout(location = 7) in float border_width;
 
 layout(location = 0) out vec4 fColor;
+layout(location = 1) flat out vec4 fBorderColor;
+layout(location = 2) out vec2 fPosition;
+layout(location = 3) flat out vec2 fCenter;
+layout(location = 4) flat out vec2 fHalfSize;
+layout(location = 5) flat out vec4 fClip;
+layout(location = 6) flat out float fCornerRadius;
+layout(location = 7) flat out float fBorderWidth;
 
 
 void main() {
@@ -39,6 +51,16 @@ void main() {
         break;
     }
 
+    vec2 half_size = (right_bottom - left_top) / 2.0;
+
     fColor = color;
+    fBorderColor = border_color;
+    fPosition = pos;
+    fCenter = left_top + half_size;
+    fHalfSize = half_size;
+    fClip = vec4(clip_left_top, clip_right_bottom);
+    // A radius larger than half the shorter side would make the corners overlap.
+    fCornerRadius = min(corner_radius, min(half_size.x, half_size.y));
+    fBorderWidth = border_width;
     gl_Position = transform * vec4(pos, 0.0, 1.0);
 }
@@ -0,0 +1,103 @@
+use crate::layout::Rect;
+use crate::renderer::{Layer, Renderer};
+use std::time::{Duration, Instant};
+use wgpu_glyph::{Scale, Section};
+
+pub const STATUS_BAR_HEIGHT: f32 = 32.0;
+const STATUS_SCALE: f32 = 22.0;
+const STATUS_PADDING: f32 = 12.0;
+/// Space between two segments.
+const SEGMENT_GAP: f32 = 24.0;
+/// How long a message stays up.
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+const SEGMENT_COLOR: [f32; 4] = [0.75, 0.75, 0.75, 1.0];
+const MESSAGE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// The strip along the bottom of the window describing the focused document, with room for a
+/// short message that goes away on its own.
+pub struct StatusBar {
+    bounds: Rect,
+    message: Option<(String, Instant)>,
+}
+
+impl StatusBar {
+    pub fn new(bounds: Rect) -> Self {
+        Self {
+            bounds,
+            message: None,
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some((message, Instant::now()));
+    }
+
+    /// Drops the message once it has been up long enough. Returns whether it did.
+    pub fn expire_message(&mut self) -> bool {
+        match &self.message {
+            Some((_, shown)) if shown.elapsed() >= MESSAGE_DURATION => {
+                self.message = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Draws `left` segments from the left edge, followed by the message, and `right` segments
+    /// against the right edge.
+    pub fn draw(&self, left: &[String], right: &[String], renderer: &mut Renderer) {
+        let bounds = self.bounds;
+        renderer.queue_rectangle(
+            Layer::Background,
+            bounds.x as i32,
+            bounds.y as i32,
+            bounds.width as i32,
+            bounds.height as i32,
+            [0.1, 0.1, 0.12, 1.0],
+        );
+        let y = bounds.y + (bounds.height - STATUS_SCALE) / 2.0;
+
+        let mut right_x = bounds.right() - STATUS_PADDING;
+        for segment in right.iter().rev() {
+            right_x -= renderer.text_width(segment, STATUS_SCALE);
+            renderer.queue_text(
+                Layer::Text,
+                Section {
+                    text: segment,
+                    screen_position: (right_x, y),
+                    scale: Scale::uniform(STATUS_SCALE),
+                    color: SEGMENT_COLOR,
+                    ..Section::default()
+                },
+            );
+            right_x -= SEGMENT_GAP;
+        }
+
+        let mut x = bounds.x + STATUS_PADDING;
+        let segments = left.iter().map(|segment| (segment.as_str(), SEGMENT_COLOR));
+        let message = self
+            .message
+            .iter()
+            .map(|(message, _)| (message.as_str(), MESSAGE_COLOR));
+        for (text, color) in segments.chain(message) {
+            renderer.queue_text(
+                Layer::Text,
+                Section {
+                    text,
+                    screen_position: (x, y),
+                    // Keep clear of the right-aligned segments.
+                    bounds: ((right_x - x).max(0.0), bounds.height),
+                    scale: Scale::uniform(STATUS_SCALE),
+                    color,
+                    ..Section::default()
+                },
+            );
+            x += renderer.text_width(text, STATUS_SCALE) + SEGMENT_GAP;
+        }
+    }
+}
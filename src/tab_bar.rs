@@ -0,0 +1,189 @@
+use crate::layout::Rect;
+use crate::renderer::{Layer, Renderer};
+use wgpu_glyph::{Scale, Section};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton},
+};
+
+pub const TAB_BAR_HEIGHT: f32 = 48.0;
+const TAB_SCALE: f32 = 28.0;
+const TAB_PADDING: f32 = 16.0;
+const MODIFIED_MARKER: &str = " \u{2022}";
+
+/// What a tab shows: the buffer title and whether it has unsaved changes.
+pub struct Tab<'a> {
+    pub title: &'a str,
+    pub modified: bool,
+}
+
+/// Something the user did to the tab strip that the editor needs to act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabAction {
+    Activate(usize),
+    Close(usize),
+    Move { from: usize, to: usize },
+}
+
+pub struct TabBar {
+    bounds: Rect,
+    // Horizontal extents of every tab from the last draw, used for hit-testing.
+    tab_extents: Vec<(f32, f32)>,
+    // Index of the tab currently being dragged with the left mouse button.
+    dragging: Option<usize>,
+    // Index of the tab the middle mouse button went down on.
+    middle_pressed: Option<usize>,
+}
+
+impl TabBar {
+    pub fn new(bounds: Rect) -> Self {
+        Self {
+            bounds,
+            tab_extents: vec![],
+            dragging: None,
+            middle_pressed: None,
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+    }
+
+    pub fn contains(&self, position: PhysicalPosition<i32>) -> bool {
+        self.bounds.contains(position)
+    }
+
+    /// Whether a tab is being dragged, in which case the tab bar wants every mouse event.
+    pub fn is_dragging(&self) -> bool {
+        self.dragging.is_some()
+    }
+
+    fn hit_test(&self, position: PhysicalPosition<i32>) -> Option<usize> {
+        let x = position.x as f32;
+        self.tab_extents
+            .iter()
+            .position(|&(start, end)| x >= start && x < end)
+    }
+
+    pub fn handle_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+        position: PhysicalPosition<i32>,
+    ) -> Option<TabAction> {
+        match (button, state) {
+            (MouseButton::Left, ElementState::Pressed) => {
+                let index = self.hit_test(position)?;
+                self.dragging = Some(index);
+                Some(TabAction::Activate(index))
+            }
+            (MouseButton::Left, ElementState::Released) => {
+                self.dragging = None;
+                None
+            }
+            (MouseButton::Middle, ElementState::Pressed) => {
+                self.middle_pressed = self.hit_test(position);
+                None
+            }
+            (MouseButton::Middle, ElementState::Released) => {
+                // Only close if the button is released over the tab it went down on.
+                let pressed = self.middle_pressed.take()?;
+                if self.hit_test(position) == Some(pressed) {
+                    Some(TabAction::Close(pressed))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn handle_mouse_move(&mut self, position: PhysicalPosition<i32>) -> Option<TabAction> {
+        let from = self.dragging?;
+        let x = position.x as f32;
+        let to = if x < self.bounds.x {
+            0
+        } else {
+            // Past the last tab the dragged tab sticks to the end.
+            self.hit_test(position)
+                .unwrap_or_else(|| self.tab_extents.len().saturating_sub(1))
+        };
+
+        if to == from {
+            return None;
+        }
+
+        self.dragging = Some(to);
+        Some(TabAction::Move { from, to })
+    }
+
+    pub fn draw(&mut self, tabs: &[Tab], active: usize, renderer: &mut Renderer) {
+        let bounds = self.bounds;
+
+        renderer.queue_rectangle(
+            Layer::Background,
+            bounds.x as i32,
+            bounds.y as i32,
+            bounds.width as i32,
+            bounds.height as i32,
+            [0.06, 0.06, 0.06, 1.0],
+        );
+
+        // Reserve room for the marker on every tab so tabs don't jump around while typing.
+        let marker_width = renderer.text_width(MODIFIED_MARKER, TAB_SCALE);
+        let text_y = bounds.y + (bounds.height - TAB_SCALE) / 2.0;
+
+        self.tab_extents.clear();
+        let mut x = bounds.x;
+        for (index, tab) in tabs.iter().enumerate() {
+            let width =
+                renderer.text_width(tab.title, TAB_SCALE) + marker_width + TAB_PADDING * 2.0;
+            self.tab_extents.push((x, x + width));
+
+            let (background, text_color) = if index == active {
+                ([0.03, 0.03, 0.03, 1.0], [1.0, 1.0, 1.0, 1.0])
+            } else {
+                ([0.1, 0.1, 0.1, 1.0], [0.6, 0.6, 0.6, 1.0])
+            };
+
+            renderer.queue_rectangle(
+                Layer::Background,
+                x as i32,
+                bounds.y as i32,
+                width as i32 - 1,
+                bounds.height as i32,
+                background,
+            );
+
+            if index == active {
+                renderer.queue_rectangle(
+                    Layer::Background,
+                    x as i32,
+                    bounds.y as i32,
+                    width as i32 - 1,
+                    2,
+                    [0.15, 0.45, 0.85, 1.0],
+                );
+            }
+
+            let text = if tab.modified {
+                format!("{}{}", tab.title, MODIFIED_MARKER)
+            } else {
+                tab.title.to_owned()
+            };
+
+            renderer.queue_text(
+                Layer::Text,
+                Section {
+                    text: &text,
+                    screen_position: (x + TAB_PADDING, text_y),
+                    scale: Scale::uniform(TAB_SCALE),
+                    color: text_color,
+                    ..Section::default()
+                },
+            );
+
+            x += width;
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! A parser for the parts of TOML the config file needs: tables, arrays of tables, dotted keys,
+//! strings, numbers, booleans, arrays and inline tables. Dates aren't supported.
+
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips spaces and tabs, but not line breaks.
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.next();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.next();
+            }
+        }
+    }
+
+    /// Skips whitespace, comments and line breaks, e.g. between array elements.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            if !self.eat('\n') && !self.eat('\r') {
+                break;
+            }
+        }
+    }
+
+    /// Expects the rest of the line to be empty apart from a comment.
+    fn end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        self.skip_comment();
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some(c) => self.error(format!("unexpected `{}` after value", c)),
+        }
+    }
+
+    fn key(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut parts = vec![];
+        loop {
+            self.skip_whitespace();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            part.push(c);
+                            self.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    if part.is_empty() {
+                        return self.error("expected a key");
+                    }
+                    part
+                }
+            };
+            parts.push(part);
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, ParseError> {
+        self.next();
+        let mut string = String::new();
+        loop {
+            // Peeked first so the error names the line the string is on.
+            let c = match self.peek() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some(c) => c,
+            };
+            self.next();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = match self.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => {
+                            let code: String = (0..4).filter_map(|_| self.next()).collect();
+                            match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                                Some(c) => c,
+                                None => return self.error(format!("invalid escape `\\u{}`", code)),
+                            }
+                        }
+                        Some(c) => return self.error(format!("invalid escape `\\{}`", c)),
+                        None => return self.error("unterminated string"),
+                    };
+                    string.push(escaped);
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, ParseError> {
+        self.next();
+        let mut string = String::new();
+        loop {
+            let c = match self.peek() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some(c) => c,
+            };
+            self.next();
+            if c == '\'' {
+                return Ok(string);
+            }
+            string.push(c);
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => {
+                self.next();
+                let mut values = vec![];
+                loop {
+                    self.skip_blank();
+                    if self.eat(']') {
+                        return Ok(Value::Array(values));
+                    }
+                    values.push(self.value()?);
+                    self.skip_blank();
+                    if self.eat(']') {
+                        return Ok(Value::Array(values));
+                    }
+                    if !self.eat(',') {
+                        return self.error("expected `,` or `]` in array");
+                    }
+                }
+            }
+            Some('{') => {
+                self.next();
+                let mut table = Map::new();
+                self.skip_whitespace();
+                if self.eat('}') {
+                    return Ok(Value::Object(table));
+                }
+                loop {
+                    let key = self.key()?;
+                    if !self.eat('=') {
+                        return self.error("expected `=` after key");
+                    }
+                    let value = self.value()?;
+                    self.insert(&mut table, &[], &key, value)?;
+                    self.skip_whitespace();
+                    if self.eat('}') {
+                        return Ok(Value::Object(table));
+                    }
+                    if !self.eat(',') {
+                        return self.error("expected `,` or `}` in inline table");
+                    }
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || "+-._".contains(c) {
+                        word.push(c);
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+                match word.as_str() {
+                    "" => self.error("expected a value"),
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _ => self.number(&word),
+                }
+            }
+        }
+    }
+
+    fn number(&self, word: &str) -> Result<Value, ParseError> {
+        let digits = word.replace('_', "");
+        if let Ok(integer) = digits.parse::<i64>() {
+            return Ok(Value::Number(integer.into()));
+        }
+        match digits.parse::<f64>().ok().and_then(Number::from_f64) {
+            Some(number) => Ok(Value::Number(number)),
+            None => self.error(format!("invalid value `{}`", word)),
+        }
+    }
+
+    /// Inserts `value` at the dotted `key` below `table`, creating tables on the way. `table`
+    /// is at `prefix`, which errors name along with the key.
+    fn insert(
+        &self,
+        table: &mut Map<String, Value>,
+        prefix: &[String],
+        key: &[String],
+        value: Value,
+    ) -> Result<(), ParseError> {
+        let (last, parents) = key.split_last().unwrap();
+        let table = self.table_at(table, parents)?;
+        if table.contains_key(last) {
+            let full_key: Vec<&str> = prefix.iter().chain(key).map(String::as_str).collect();
+            return self.error(format!("`{}` is defined twice", full_key.join(".")));
+        }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
+    /// The table at the dotted `key`, created if it doesn't exist yet. For arrays of tables this
+    /// is the last table in the array.
+    fn table_at<'t>(
+        &self,
+        mut table: &'t mut Map<String, Value>,
+        key: &[String],
+    ) -> Result<&'t mut Map<String, Value>, ParseError> {
+        for part in key {
+            let entry = table
+                .entry(part.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+            table = match entry {
+                Value::Object(table) => table,
+                Value::Array(tables) => match tables.last_mut() {
+                    Some(Value::Object(table)) => table,
+                    _ => return self.error(format!("`{}` is not a table", part)),
+                },
+                _ => return self.error(format!("`{}` is not a table", part)),
+            };
+        }
+        Ok(table)
+    }
+}
+
+/// Parses a TOML document into a table.
+pub fn parse(text: &str) -> Result<Map<String, Value>, ParseError> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        line: 1,
+    };
+    let mut root = Map::new();
+    // The key of the `[table]` that key/value pairs currently go into.
+    let mut current: Vec<String> = vec![];
+
+    loop {
+        parser.skip_blank();
+        match parser.peek() {
+            None => return Ok(root),
+            Some('[') => {
+                parser.next();
+                let array = parser.eat('[');
+                let key = parser.key()?;
+                if !parser.eat(']') || (array && !parser.eat(']')) {
+                    return parser.error("expected `]` after table name");
+                }
+
+                if array {
+                    let (last, parents) = key.split_last().unwrap();
+                    let parent = parser.table_at(&mut root, parents)?;
+                    let entry = parent
+                        .entry(last.clone())
+                        .or_insert_with(|| Value::Array(vec![]));
+                    match entry {
+                        Value::Array(tables) => tables.push(Value::Object(Map::new())),
+                        _ => return parser.error(format!("`{}` is not an array", key.join("."))),
+                    }
+                } else {
+                    parser.table_at(&mut root, &key)?;
+                }
+                // Only now, so errors above name the line of the table.
+                parser.end_of_line()?;
+                current = key;
+            }
+            Some(_) => {
+                let key = parser.key()?;
+                if !parser.eat('=') {
+                    return parser.error("expected `=` after key");
+                }
+                let value = parser.value()?;
+                let table = parser.table_at(&mut root, &current)?;
+                parser.insert(table, &current, &key, value)?;
+                parser.end_of_line()?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parsed(text: &str) -> Value {
+        Value::Object(parse(text).unwrap())
+    }
+
+    #[test]
+    fn tables() {
+        let text = "top = 1\n\n[a]\nx = \"y\"\n\n[a.b] # nested\nz = true\n";
+        assert_eq!(
+            parsed(text),
+            json!({ "top": 1, "a": { "x": "y", "b": { "z": true } } })
+        );
+    }
+
+    #[test]
+    fn arrays_of_tables() {
+        let text = "[[bin]]\nname = 'a'\n\n[[bin]]\nname = 'b'\n[bin.extra]\nflag = false\n";
+        assert_eq!(
+            parsed(text),
+            json!({ "bin": [{ "name": "a" }, { "name": "b", "extra": { "flag": false } }] })
+        );
+    }
+
+    #[test]
+    fn dotted_keys() {
+        let text = "a.b.c = 1\na.d = 2\n\"quoted.key\".e = 3\n";
+        assert_eq!(
+            parsed(text),
+            json!({ "a": { "b": { "c": 1 }, "d": 2 }, "quoted.key": { "e": 3 } })
+        );
+    }
+
+    #[test]
+    fn inline_tables_and_arrays() {
+        let text = "point = { x = 1, y = -2.5, tag.name = \"p\" }\nlist = [\n  1,\n  2, # two\n]\n";
+        assert_eq!(
+            parsed(text),
+            json!({ "point": { "x": 1, "y": -2.5, "tag": { "name": "p" } }, "list": [1, 2] })
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let text = r#"basic = "tab\tnewline\nquote\"backslash\\e\u00e9"
+literal = 'C:\path\n'
+"#;
+        assert_eq!(
+            parsed(text),
+            json!({ "basic": "tab\tnewline\nquote\"backslash\\e\u{e9}", "literal": "C:\\path\\n" })
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let error = parse("a = 1\nb = 2\na = 3\n").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.to_string(), "line 3: `a` is defined twice");
+
+        let error = parse("[t]\nx = 1\n\n[t]\nx = 2\n").unwrap_err();
+        assert_eq!(error.line, 5);
+        assert_eq!(error.message, "`t.x` is defined twice");
+
+        let error = parse("p = { x = 1, x = 2 }").unwrap_err();
+        assert_eq!(error.message, "`x` is defined twice");
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = parse("a = 1\n\n# comment\nb =\n").unwrap_err();
+        assert_eq!(error.line, 4);
+        assert_eq!(error.message, "expected a value");
+
+        let error = parse("a = \"open\nb = 1\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: unterminated string");
+
+        let error = parse("a = 1\n[a]\n").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = parse("x = 1 2\n").unwrap_err();
+        assert_eq!(error.message, "unexpected `2` after value");
+
+        let error = parse("x = \"\\q\"\n").unwrap_err();
+        assert_eq!(error.message, "invalid escape `\\q`");
+    }
+}
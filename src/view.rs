@@ -0,0 +1,647 @@
+use crate::config::ViewSettings;
+use crate::document::{Change, Document, Location, Span};
+use crate::layout::Rect;
+use crate::pane::Direction;
+use crate::rectangle_brush::RectStyle;
+use crate::renderer::{Layer, Renderer};
+use crate::session::ViewState;
+use std::{borrow::Cow, collections::HashMap, ops::Range};
+use wgpu_glyph::{BuiltInLineBreaker, Font, Layout, Point, Scale, SectionText, VariedSection};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton},
+};
+
+/// Rounds the ends of the selection on each line.
+const SELECTION_RADIUS: f32 = 3.0;
+
+/// The text drawn for a line. Control characters such as a stray '\r' would be drawn as missing
+/// glyphs or break the line, so they are shown as '?'. Byte offsets stay the same, which keeps
+/// the highlight ranges and cursor columns valid.
+fn displayed_text(line: &str) -> Cow<'_, str> {
+    if line.chars().any(|c| c.is_ascii_control() && c != '\t') {
+        Cow::Owned(
+            line.chars()
+                .map(|c| {
+                    if c.is_ascii_control() && c != '\t' {
+                        '?'
+                    } else {
+                        c
+                    }
+                })
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cursor {
+    location: Location,
+    col_affinity: usize,
+    selection_start: Option<Location>,
+}
+
+impl Cursor {
+    fn new() -> Self {
+        Self {
+            location: Location::new(),
+            col_affinity: 0,
+            selection_start: None,
+        }
+    }
+
+    fn set_row(&mut self, row: usize) {
+        self.location.row = row;
+    }
+
+    fn set_col(&mut self, col: usize) {
+        self.location.col = col;
+    }
+
+    fn set_col_with_affinity(&mut self, col: usize) {
+        self.location.col = col;
+        self.col_affinity = col;
+    }
+
+    fn set_location(&mut self, location: Location) {
+        self.location = location;
+        self.col_affinity = location.col;
+    }
+
+    /// Takes the current selection and creates a span.
+    /// Returns `None` if nothing is selected.
+    fn selection_span(&self) -> Option<Span> {
+        let selection_start = self.selection_start?;
+
+        Some(Span::new(selection_start, self.location))
+    }
+}
+
+/// One place a document is shown: owns the cursor, selection, scroll position and the
+/// area of the window it is drawn in.
+#[derive(Debug, Clone)]
+pub struct View {
+    scroll: f32,
+    cursor: Cursor,
+    dragging: bool,
+    bounds: Rect,
+    settings: ViewSettings,
+    // What was drawn last time, to draw it again without laying it out.
+    frame: Option<Frame>,
+}
+
+impl View {
+    pub fn new(bounds: Rect) -> Self {
+        Self {
+            scroll: 0.0,
+            cursor: Cursor::new(),
+            dragging: false,
+            bounds,
+            settings: ViewSettings::default(),
+            frame: None,
+        }
+    }
+
+    /// Recreates a view from a saved session. Call `clamp_to` afterwards in case the document
+    /// changed since.
+    pub fn from_state(bounds: Rect, state: &ViewState) -> Self {
+        let mut view = Self::new(bounds);
+        view.scroll = state.scroll.max(0.0);
+        view.cursor.set_location(state.cursor);
+        view.cursor.selection_start = state.selection_start;
+        view
+    }
+
+    pub fn state(&self) -> ViewState {
+        ViewState {
+            cursor: self.cursor.location,
+            selection_start: self.cursor.selection_start,
+            scroll: self.scroll,
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        self.frame = None;
+    }
+
+    pub fn set_settings(&mut self, settings: ViewSettings) {
+        self.settings = settings;
+        self.frame = None;
+    }
+
+    /// Keeps the cursor and selection on the same text after another view edited the document.
+    pub fn adjust_for_change(&mut self, change: &Change) {
+        self.cursor.location.adjust(change);
+        self.cursor.col_affinity = self.cursor.location.col;
+        if let Some(selection_start) = self.cursor.selection_start.as_mut() {
+            selection_start.adjust(change);
+        }
+    }
+
+    /// Pulls the cursor back inside `document`, e.g. after switching which document is shown.
+    pub fn clamp_to(&mut self, document: &Document) {
+        self.cursor.location = document.clamp(self.cursor.location);
+        self.cursor.selection_start = self
+            .cursor
+            .selection_start
+            .map(|location| document.clamp(location));
+    }
+
+    fn ensure_cursor_in_view(&mut self) {
+        let scale = self.settings.font_size;
+        let cursor_y = self.cursor.location.row as f32 * scale;
+        let bottom = self.scroll + self.bounds.height;
+
+        if cursor_y < self.scroll {
+            self.scroll = cursor_y;
+        } else if cursor_y + scale > bottom {
+            self.scroll = cursor_y - self.bounds.height + scale + 5.0;
+        }
+    }
+
+    pub fn scroll(&mut self, document: &Document, delta: f32) {
+        let scale = self.settings.font_size;
+        // TODO: Find better way to calculate max scroll based on line count
+        let max_scroll = ((document.line_count() - 1) as f32 * scale) + 5.0;
+
+        self.scroll = (self.scroll + delta).max(0.0).min(max_scroll);
+    }
+
+    pub fn handle_mouse_input(
+        &mut self,
+        document: &Document,
+        button: MouseButton,
+        state: ElementState,
+        position: PhysicalPosition<i32>,
+    ) {
+        if button == MouseButton::Left {
+            if state == ElementState::Pressed {
+                self.cursor.selection_start = None;
+                let location = self.hit_test(document, position);
+                self.cursor.set_location(location);
+                self.dragging = true;
+            } else {
+                self.dragging = false;
+            }
+        }
+    }
+
+    /// Extends the selection while dragging. Returns whether it changed.
+    pub fn handle_mouse_move(
+        &mut self,
+        document: &Document,
+        position: PhysicalPosition<i32>,
+    ) -> bool {
+        if !self.dragging {
+            return false;
+        }
+        let before = (self.cursor.location, self.cursor.selection_start);
+        if self.cursor.selection_start.is_none() {
+            self.cursor.selection_start = Some(self.cursor.location);
+        }
+        let location = self.hit_test(document, position);
+        self.cursor.set_location(location);
+        before != (self.cursor.location, self.cursor.selection_start)
+    }
+
+    fn hit_test(&self, document: &Document, position: PhysicalPosition<i32>) -> Location {
+        let scale = self.settings.font_size;
+        let x_pad = self.settings.padding;
+        let digit_count = document.line_count().to_string().chars().count();
+        let gutter_offset = x_pad + 30.0 + digit_count as f32 * (scale / 2.0);
+
+        let abs_position = PhysicalPosition::new(
+            (position.x as f32 - self.bounds.x - gutter_offset).max(0.0),
+            (position.y as f32 - self.bounds.y + self.scroll).max(0.0),
+        );
+
+        let line = (abs_position.y / scale).floor() as usize;
+        let h_advance = self.settings.char_width;
+        // Counted in characters, which are all the same width.
+        let col = (abs_position.x / h_advance).round() as usize;
+        if line >= document.line_count() {
+            document.location_at(line, usize::MAX)
+        } else {
+            document.location_at(line, col)
+        }
+    }
+
+    /// Applies typed text to `document`, returning the change so other views can follow along.
+    pub fn handle_char_input(&mut self, document: &mut Document, input: char) -> Option<Change> {
+        let location = self.cursor.location;
+        let change = if input == '\n' || input == '\r' {
+            let change = document.insert(location, "\n");
+            self.cursor.set_location(change.new_end);
+            change
+        // this is Backspace
+        } else if input == '\u{8}' {
+            let start = if location.col > 0 {
+                Location {
+                    row: location.row,
+                    col: document
+                        .line(location.row)
+                        .floor_char_boundary(location.col - 1),
+                }
+            } else if location.row > 0 {
+                Location {
+                    row: location.row - 1,
+                    col: document.line(location.row - 1).len(),
+                }
+            } else {
+                return None;
+            };
+            let change = document.delete(Span::new(start, location));
+            self.cursor.set_location(change.new_end);
+            change
+        // this is Delete
+        } else if input == '\u{7f}' {
+            if document.line(location.row).len() <= location.col {
+                return None;
+            }
+            let end = Location {
+                row: location.row,
+                col: document
+                    .line(location.row)
+                    .ceil_char_boundary(location.col + 1),
+            };
+            document.delete(Span::new(location, end))
+        } else if input == '\t' {
+            let language = self.settings.language;
+            let indent = if language.insert_spaces {
+                // Pad to the next tab stop.
+                " ".repeat(language.tab_size - location.col % language.tab_size)
+            } else {
+                // TODO: Tabs are still drawn as a single narrow glyph.
+                "\t".to_owned()
+            };
+            let change = document.insert(location, &indent);
+            self.cursor.set_col(change.new_end.col);
+            change
+        } else if input.is_control() {
+            // Keys like Escape come through as control characters, they aren't text.
+            return None;
+        } else {
+            let change = document.insert(location, &input.to_string());
+            self.cursor.set_col(change.new_end.col);
+            change
+        };
+        self.ensure_cursor_in_view();
+        Some(change)
+    }
+
+    /// Reverts the last edit made to `document`, from whichever view it was made in.
+    pub fn undo(&mut self, document: &mut Document) -> Option<Change> {
+        let (change, location) = document.undo()?;
+        self.cursor.selection_start = None;
+        self.cursor.set_location(location);
+        self.ensure_cursor_in_view();
+        Some(change)
+    }
+
+    pub fn redo(&mut self, document: &mut Document) -> Option<Change> {
+        let (change, location) = document.redo()?;
+        self.cursor.selection_start = None;
+        self.cursor.set_location(location);
+        self.ensure_cursor_in_view();
+        Some(change)
+    }
+
+    /// Moves the cursor to `location`, dropping the selection, and scrolls it to the middle of
+    /// the view.
+    pub fn go_to(&mut self, document: &Document, location: Location) {
+        self.cursor.set_location(document.clamp(location));
+        self.cursor.selection_start = None;
+        let scale = self.settings.font_size;
+        let cursor_y = self.cursor.location.row as f32 * scale;
+        self.scroll = cursor_y - (self.bounds.height - scale) / 2.0;
+        self.scroll(document, 0.0);
+    }
+
+    pub fn cursor_location(&self) -> Location {
+        self.cursor.location
+    }
+
+    /// How many characters are selected, counting each line break as one.
+    pub fn selection_len(&self, document: &Document) -> usize {
+        let span = match self.cursor.selection_span() {
+            Some(span) => span,
+            None => return 0,
+        };
+        (span.start.row..=span.end.row)
+            .map(|row| {
+                let line = document.line(row);
+                let (start, end) = span.get_char_indices_for_line(row, line.len()).unwrap();
+                let chars = line.get(start..end).map_or(0, |text| text.chars().count());
+                // The line break at the end of every line but the last is selected too.
+                chars + (row < span.end.row) as usize
+            })
+            .sum()
+    }
+
+    pub fn move_cursor(&mut self, document: &Document, direction: Direction) {
+        // TODO: Support changing selection via Shift modifier and arrow keys!
+        // Should be pretty easy: don't reset selection start if Shift modifier is active.
+        match direction {
+            Direction::Up => {
+                self.cursor.selection_start = None;
+                let row = (self.cursor.location.row as isize - 1)
+                    .max(0)
+                    .min(document.line_count() as isize) as usize;
+                let col = document
+                    .line(row)
+                    .floor_char_boundary(self.cursor.col_affinity);
+                self.cursor.set_row(row);
+                self.cursor.set_col(col);
+            }
+            Direction::Down => {
+                self.cursor.selection_start = None;
+                let row = (self.cursor.location.row as isize + 1)
+                    .max(0)
+                    .min(document.line_count() as isize - 1) as usize;
+                let col = document
+                    .line(row)
+                    .floor_char_boundary(self.cursor.col_affinity);
+                self.cursor.set_row(row);
+                self.cursor.set_col(col);
+            }
+            Direction::Left => {
+                self.cursor.selection_start = None;
+                if self.cursor.location.col == 0 {
+                    if self.cursor.location.row > 0 {
+                        self.cursor.set_row(self.cursor.location.row - 1);
+                        self.cursor
+                            .set_col_with_affinity(document.line(self.cursor.location.row).len());
+                    }
+                } else {
+                    let line = document.line(self.cursor.location.row);
+                    self.cursor.set_col_with_affinity(
+                        line.floor_char_boundary(self.cursor.location.col - 1),
+                    );
+                }
+            }
+            Direction::Right => {
+                self.cursor.selection_start = None;
+                if self.cursor.location.col >= document.line(self.cursor.location.row).len() {
+                    if self.cursor.location.row < document.line_count() - 1 {
+                        self.cursor.set_row(self.cursor.location.row + 1);
+                        self.cursor.set_col_with_affinity(0);
+                    }
+                } else {
+                    let line = document.line(self.cursor.location.row);
+                    self.cursor.set_col_with_affinity(
+                        line.ceil_char_boundary(self.cursor.location.col + 1),
+                    );
+                }
+            }
+        }
+        self.ensure_cursor_in_view();
+    }
+
+    pub fn draw(&mut self, document: &Document, focused: bool, renderer: &mut Renderer) {
+        let key = FrameKey {
+            generation: document.highlight_generation(),
+            focused,
+            scroll: self.scroll,
+            cursor: self.cursor.location,
+            selection_start: self.cursor.selection_start,
+        };
+        let frame = match self.frame.take() {
+            Some(frame) if frame.key == key => frame,
+            previous => self.layout_frame(document, key, previous, renderer.font()),
+        };
+
+        // Lines scrolled partly out of view are cut off at the edges.
+        renderer.push_clip(self.bounds);
+        let scale = self.settings.font_size;
+        for (layer, rect, style) in &frame.rects {
+            renderer.queue_styled_rectangle(*layer, *rect, *style);
+        }
+        let x_pad = self.settings.padding;
+        for (line, y, number_color) in &frame.lines {
+            renderer.queue_text(
+                Layer::Text,
+                VariedSection {
+                    screen_position: (self.bounds.x + x_pad, *y),
+                    text: vec![SectionText {
+                        text: &line.number,
+                        scale: Scale::uniform(scale),
+                        color: *number_color,
+                        ..SectionText::default()
+                    }],
+                    ..VariedSection::default()
+                },
+            );
+
+            let text = line
+                .runs
+                .iter()
+                .map(|(range, color)| SectionText {
+                    text: &line.text[range.clone()],
+                    scale: Scale::uniform(scale),
+                    color: *color,
+                    ..SectionText::default()
+                })
+                .collect();
+            // Cut the line off at the edge of the view rather than wrapping it.
+            renderer.queue_text(
+                Layer::Text,
+                VariedSection {
+                    screen_position: (frame.gutter_offset, *y),
+                    bounds: ((self.bounds.right() - frame.gutter_offset).max(0.0), scale),
+                    layout: Layout::default_single_line()
+                        .line_breaker(BuiltInLineBreaker::AnyCharLineBreaker),
+                    text,
+                    ..VariedSection::default()
+                },
+            );
+        }
+        renderer.pop_clip();
+        self.frame = Some(frame);
+    }
+
+    /// Works out what to draw for `key`. Lines of `previous` whose text is still shown are
+    /// reused wherever that text moved to, so an edit only lays out the lines it changed.
+    fn layout_frame(
+        &self,
+        document: &Document,
+        key: FrameKey,
+        previous: Option<Frame>,
+        font: &Font,
+    ) -> Frame {
+        let mut cached: HashMap<String, Vec<CachedLine>> = HashMap::new();
+        for (line, _, _) in previous.map(|previous| previous.lines).unwrap_or_default() {
+            cached.entry(line.text.clone()).or_default().push(line);
+        }
+
+        let scale = self.settings.font_size;
+        let colors = &self.settings.colors;
+        let bounds = self.bounds;
+        let x_pad = self.settings.padding;
+        let digit_count = document.line_count().to_string().chars().count();
+        let gutter_offset = bounds.x + x_pad + 30.0 + digit_count as f32 * (scale / 2.0);
+        let mut frame = Frame {
+            key,
+            gutter_offset,
+            rects: vec![],
+            lines: vec![],
+        };
+
+        // gutter color
+        frame.rects.push((
+            Layer::Background,
+            Rect::new(
+                bounds.x,
+                bounds.y,
+                digit_count as f32 * (scale / 2.0) + x_pad * 2.0,
+                bounds.height,
+            ),
+            RectStyle::solid(colors.gutter),
+        ));
+
+        let selection_span = self.cursor.selection_span();
+        let first_row = (self.scroll / scale).max(0.0) as usize;
+        let mut y = bounds.y + 5.0 - self.scroll + first_row as f32 * scale;
+
+        for index in first_row..document.line_count() {
+            if y + scale <= bounds.y {
+                y += scale;
+                continue;
+            }
+            if y >= bounds.bottom() {
+                break;
+            }
+
+            let text = displayed_text(document.line(index));
+            let line = match cached.get_mut(text.as_ref()).and_then(Vec::pop) {
+                Some(line) => line.moved_to(document, index),
+                None => CachedLine::new(text.into_owned(), document, index, scale, font),
+            };
+            let mut line_no_color = colors.line_number;
+
+            // Paint selection boxes
+            if let Some((start, end)) = selection_span
+                .and_then(|span| span.get_char_indices_for_line(index, line.text.len()))
+            {
+                let x = line.x_at(start) + gutter_offset;
+                let width = (line.x_at(end) + gutter_offset - x)
+                    .min(bounds.right() - x)
+                    .max(0.0);
+                frame.rects.push((
+                    Layer::Overlay,
+                    Rect::new(x, y, width, scale),
+                    RectStyle {
+                        color: colors.selection,
+                        corner_radius: SELECTION_RADIUS,
+                        ..RectStyle::default()
+                    },
+                ));
+            }
+
+            if index == self.cursor.location.row {
+                line_no_color = colors.active_line_number;
+                let cursor_x = gutter_offset + line.x_at(self.cursor.location.col);
+                // Only the focused view gets the active line, other views keep a dimmed cursor.
+                let cursor_color = if key.focused {
+                    frame.rects.push((
+                        Layer::Background,
+                        Rect::new(bounds.x, y, bounds.width, scale),
+                        RectStyle::solid(colors.active_line),
+                    ));
+                    colors.cursor
+                } else {
+                    let [r, g, b, a] = colors.cursor;
+                    [r, g, b, a * 0.3]
+                };
+
+                if cursor_x < bounds.right() {
+                    frame.rects.push((
+                        Layer::Overlay,
+                        Rect::new(cursor_x - 2.0, y, 4.0, scale),
+                        RectStyle::solid(cursor_color),
+                    ));
+                }
+            }
+
+            frame.lines.push((line, y, line_no_color));
+            y += scale;
+        }
+        frame
+    }
+}
+
+/// What a view's frame depends on besides its settings and bounds, which drop the frame when
+/// they change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FrameKey {
+    generation: usize,
+    focused: bool,
+    scroll: f32,
+    cursor: Location,
+    selection_start: Option<Location>,
+}
+
+/// Everything a view queued for its last frame, queued again as is while nothing it depends on
+/// changes.
+#[derive(Debug, Clone)]
+struct Frame {
+    key: FrameKey,
+    gutter_offset: f32,
+    rects: Vec<(Layer, Rect, RectStyle)>,
+    /// Each line with where it is drawn and the colour of its number.
+    lines: Vec<(CachedLine, f32, [f32; 4])>,
+}
+
+/// A line laid out for drawing.
+#[derive(Debug, Clone)]
+struct CachedLine {
+    number: String,
+    text: String,
+    runs: Vec<(Range<usize>, [f32; 4])>,
+    /// Where each character starts, and where the last one ends, from the start of the line.
+    offsets: Vec<f32>,
+}
+
+impl CachedLine {
+    /// Lays out `text`, the displayed text of the line at `row`.
+    fn new(text: String, document: &Document, row: usize, scale: f32, font: &Font) -> Self {
+        let mut offsets = vec![0.0];
+        // TODO: Gah, we should not do this. We should do a single layout pass and add some
+        // methods that lets us query glyph locations.
+        let layout = font.layout(&text, Scale::uniform(scale), Point { x: 0.0, y: 0.0 });
+        let mut x = 0.0;
+        for glyph in layout {
+            x += glyph.unpositioned().h_metrics().advance_width;
+            offsets.push(x);
+        }
+        Self {
+            number: (row + 1).to_string(),
+            runs: document.highlight_info()[row].clone(),
+            text,
+            offsets,
+        }
+    }
+
+    /// This line shown as the line at `row`, which has the same text but may be highlighted
+    /// differently.
+    fn moved_to(mut self, document: &Document, row: usize) -> Self {
+        self.number = (row + 1).to_string();
+        self.runs = document.highlight_info()[row].clone();
+        self
+    }
+
+    /// Where the character at byte `col` starts.
+    fn x_at(&self, col: usize) -> f32 {
+        let chars = self
+            .text
+            .get(..col)
+            .map_or(self.offsets.len() - 1, |before| before.chars().count());
+        self.offsets[chars.min(self.offsets.len() - 1)]
+    }
+}